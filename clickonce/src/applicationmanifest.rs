@@ -0,0 +1,361 @@
+//! Models for ClickOnce application manifest
+//!
+use crate::deploymentmanifest::{DependencyType, Hash, ProcessorArchitecture, PublisherIdentity};
+use crate::signature::Signature;
+use crate::FromXML;
+use serde::Deserialize;
+
+/// Model for ClickOnce application manifest
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/clickonce-application-manifest?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+#[serde(transparent)]
+pub struct ApplicationManifest {
+    /// Required. The top-level element for the application manifest.
+    pub assembly: Assembly,
+}
+
+impl FromXML<'_> for ApplicationManifest {}
+
+/// Model for <assembly> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/assembly-element-clickonce-application?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Assembly {
+    /// Attribute. Required. This attribute must be set to 1.0.
+    #[serde(alias = "manifestVersion")]
+    pub manifest_version: String,
+
+    /// Child. Required. Identifies the primary assembly of the ClickOnce application.
+    #[serde(alias = "assemblyIdentity")]
+    pub assembly_identity: AssemblyIdentity,
+
+    /// Child. Required. Identifies the assembly that should be executed when this ClickOnce
+    /// application is run on a client computer.
+    #[serde(alias = "entryPoint")]
+    pub entry_point: Option<EntryPoint>,
+
+    /// Child. Optional. Identifies a platform or assembly that is required for the application.
+    #[serde(default)]
+    pub dependency: Vec<Dependency>,
+
+    /// Child. Optional. Identifies all nonassembly files downloaded and used by the application.
+    #[serde(default)]
+    pub file: Vec<File>,
+
+    /// Child. Required for signed manifests. Identifies the publisher that signed the manifest.
+    #[serde(alias = "publisherIdentity")]
+    pub publisher_identity: Option<PublisherIdentity>,
+
+    /// Child. Required for signed manifests. The XML digital signature over the manifest.
+    #[serde(alias = "Signature")]
+    pub signature: Option<Signature>,
+}
+
+impl Assembly {
+    /// The assemblies that must already be installed, such as the .NET
+    /// runtime, which the bootstrapper installs from its prerequisite
+    /// packages rather than ClickOnce downloading them.
+    pub fn prerequisites(&self) -> impl Iterator<Item = &DependentAssembly> {
+        self.dependency
+            .iter()
+            .filter_map(|dependency| dependency.dependent_assembly.as_ref())
+            .filter(|assembly| matches!(assembly.dependency_type, DependencyType::Prerequisite))
+    }
+}
+
+/// Model for <assemblyIdentity> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/assemblyidentity-element-clickonce-application?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct AssemblyIdentity {
+    /// Attribute. Required. Identifies the name of the application.
+    pub name: String,
+
+    /// Attribute. Required. Specifies the version number of the application, in the following format:
+    /// major.minor.build.revision.
+    pub version: String,
+
+    /// Attribute. Optional. Specifies a 16-character hexadecimal string that represents the last 8
+    /// bytes of the SHA-1 hash value of the public key under which the application or assembly is signed.
+    #[serde(alias = "publicKeyToken")]
+    pub public_key_token: Option<String>,
+
+    /// Attribute. Optional. Identifies the two part language codes (for example, en-US) of the
+    /// assembly.
+    pub language: Option<String>,
+
+    /// Attribute. Optional. Specifies the processor.
+    #[serde(alias = "processorArchitecture")]
+    pub processor_architecture: Option<ProcessorArchitecture>,
+
+    /// Attribute. Optional. For compatibility with Windows side-by-side install technology. The only
+    /// allowed value is win32.
+    #[serde(alias = "type")]
+    pub r#type: Option<String>,
+}
+
+/// Model for <entryPoint> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/entrypoint-element-clickonce-application?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct EntryPoint {
+    /// Attribute. Optional. Not used by the .NET Framework.
+    pub name: Option<String>,
+
+    /// Child. Required. The assembly containing the entry point.
+    #[serde(alias = "assemblyIdentity")]
+    pub assembly_identity: AssemblyIdentity,
+
+    /// Child. Required. The executable to run, and its parameters.
+    #[serde(alias = "commandLine")]
+    pub command_line: CommandLine,
+}
+
+/// Model for <commandLine> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct CommandLine {
+    /// Attribute. Required. The local name of the startup assembly for the ClickOnce application.
+    pub file: String,
+
+    /// Attribute. Required. Describes the action to take from the entry point. The only valid value
+    /// is run; if a blank string is supplied, run is assumed.
+    pub parameters: Option<String>,
+}
+
+/// Model for <dependency> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/dependency-element-clickonce-application?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Dependency {
+    /// Child. Optional. Specifies a minimum version of the operating system that the application
+    /// requires.
+    #[serde(alias = "dependentOS")]
+    pub dependent_os: Option<DependentOS>,
+
+    /// Child. Optional. Contains an assembly required by the application.
+    #[serde(alias = "dependentAssembly")]
+    pub dependent_assembly: Option<DependentAssembly>,
+}
+
+/// Model for <dependentOS> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct DependentOS {
+    /// Child. Required. Parent of os.
+    #[serde(alias = "osVersionInfo")]
+    pub os_version_info: OSVersionInfo,
+}
+
+/// Model for <osVersionInfo> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct OSVersionInfo {
+    /// Child. Required. The minimum operating system version.
+    pub os: OS,
+}
+
+/// Model for <os> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct OS {
+    /// Attribute. Required. Specifies the major version number of the OS.
+    #[serde(alias = "majorVersion")]
+    pub major_version: u32,
+
+    /// Attribute. Required. Specifies the minor version number of the OS.
+    #[serde(alias = "minorVersion")]
+    pub minor_version: u32,
+
+    /// Attribute. Required. Specifies the build number of the OS.
+    #[serde(alias = "buildNumber")]
+    pub build_number: u32,
+
+    /// Attribute. Required. Specifies the service pack major number of the OS.
+    #[serde(alias = "servicePackMajor")]
+    pub service_pack_major: u32,
+}
+
+/// Model for <dependentAssembly> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct DependentAssembly {
+    /// Attribute. Required. Specifies the dependency type.
+    #[serde(alias = "dependencyType")]
+    pub dependency_type: DependencyType,
+
+    /// Attribute. Optional. Specifies that the assembly can be loaded after the application starts.
+    #[serde(alias = "allowDelayedBinding")]
+    pub allow_delayed_binding: Option<bool>,
+
+    /// Attribute. Optional. Specifies the full path to the assembly, relative to the application
+    /// manifest.
+    pub codebase: Option<String>,
+
+    /// Attribute. Optional. The size of the assembly, in bytes.
+    pub size: Option<u64>,
+
+    /// Child. Required. Identifies the assembly.
+    #[serde(alias = "assemblyIdentity")]
+    pub assembly_identity: AssemblyIdentity,
+
+    /// Child. Optional. The hash of the assembly.
+    pub hash: Option<Hash>,
+}
+
+/// Model for <file> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/file-element-clickonce-application?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct File {
+    /// Attribute. Required. Identifies the name of the file, relative to the application manifest.
+    pub name: String,
+
+    /// Attribute. Required. Specifies the size, in bytes, of the file.
+    pub size: u64,
+
+    /// Attribute. Optional. If the optional attribute is set to true, specifies the name of the
+    /// group of files to download on demand.
+    pub group: Option<String>,
+
+    /// Attribute. Optional. Specifies whether this file must download when the application is
+    /// first run, or whether the file should reside only on a server until the application
+    /// requests it on demand.
+    pub optional: Option<bool>,
+
+    /// Attribute. Optional. Specifies that this file is a data file.
+    #[serde(alias = "writeableType")]
+    pub writeable_type: Option<String>,
+
+    /// Child. Optional. The hash of the file.
+    pub hash: Option<Hash>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn application_manifest() -> &'static [u8] {
+        include_bytes!("example/sample.exe.manifest")
+    }
+
+    #[test]
+    fn parse_manifest() {
+        let manifest = ApplicationManifest::from_xml_bytes(application_manifest())
+            .expect("Failure parsing manifest");
+        assert_eq!(manifest.assembly.dependency.len(), 3);
+        assert_eq!(manifest.assembly.file.len(), 3);
+        assert_eq!(
+            manifest.assembly.file[1].name,
+            "CardDataSource\\client_DMU.xml"
+        );
+        assert_eq!(manifest.assembly.file[2].optional, Some(true));
+        assert_eq!(manifest.assembly.prerequisites().count(), 1);
+    }
+}
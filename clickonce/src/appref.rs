@@ -0,0 +1,209 @@
+//! Reading and writing `.appref-ms` application shortcuts
+//!
+//! Installing a ClickOnce application creates a Start menu shortcut with
+//! the `.appref-ms` extension. It holds a single line naming the deployment
+//! manifest URL and the deployment's identity, separated by `#`:
+//!
+//! ```text
+//! http://example.com/App.application#App.application, Culture=neutral, PublicKeyToken=0123456789abcdef, processorArchitecture=msil
+//! ```
+//!
+//! Windows writes these files as UTF-16LE with a byte order mark.
+use crate::deploymentmanifest::DeploymentManifest;
+use crate::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The contents of an `.appref-ms` shortcut
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationReference {
+    /// URL of the deployment manifest the application was installed from
+    pub codebase: String,
+
+    /// Name of the deployment, matching the `name` of its `<assemblyIdentity>`
+    pub name: String,
+
+    /// Culture of the deployment, usually `neutral`
+    pub culture: Option<String>,
+
+    /// Public key token of the deployment's publisher
+    pub public_key_token: Option<String>,
+
+    /// Processor architecture of the deployment, such as `msil` or `x86`
+    pub processor_architecture: Option<String>,
+}
+
+impl ApplicationReference {
+    /// Reads a shortcut from raw file contents, which may be UTF-8 or
+    /// UTF-16 with a byte order mark.
+    pub fn from_bytes(contents: &[u8]) -> Result<Self, Error> {
+        crate::encoding::decode(contents)?.parse()
+    }
+
+    /// Encodes the shortcut as Windows writes it, in UTF-16LE with a byte
+    /// order mark.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xfe];
+        for unit in self.to_string().encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Whether `manifest` is the deployment this shortcut refers to, going
+    /// by its name and public key token.
+    pub fn matches(&self, manifest: &DeploymentManifest) -> bool {
+        let identity = &manifest.assembly.assembly_identity;
+        identity.name.eq_ignore_ascii_case(&self.name)
+            && self
+                .public_key_token
+                .as_deref()
+                .is_none_or(|token| identity.public_key_token.eq_ignore_ascii_case(token))
+    }
+
+    /// Resolves the shortcut against the deployment manifest fetched from
+    /// its codebase, returning the URL updates should be taken from.
+    ///
+    /// The manifest's `<deploymentProvider>` takes precedence over the URL
+    /// in the shortcut, as it does for ClickOnce itself. Fails if the
+    /// manifest belongs to a different deployment.
+    pub fn resolve(&self, manifest: &DeploymentManifest) -> Result<String, Error> {
+        if !self.matches(manifest) {
+            let identity = &manifest.assembly.assembly_identity;
+            return Err(Error::InvalidShortcut(format!(
+                "shortcut refers to {} ({}), but {} is {} ({})",
+                self.name,
+                self.public_key_token.as_deref().unwrap_or("unsigned"),
+                self.codebase,
+                identity.name,
+                identity.public_key_token
+            )));
+        }
+        let provider = manifest
+            .assembly
+            .deployment
+            .as_ref()
+            .and_then(|deployment| deployment.deployment_provider.as_ref());
+        Ok(provider.map_or_else(
+            || self.codebase.clone(),
+            |provider| provider.codebase.clone(),
+        ))
+    }
+}
+
+impl FromStr for ApplicationReference {
+    type Err = Error;
+
+    fn from_str(contents: &str) -> Result<Self, Error> {
+        let contents = contents
+            .trim_start_matches('\u{feff}')
+            .trim_end_matches(|c: char| c == '\0' || c.is_whitespace());
+        let (codebase, identity) = contents
+            .split_once('#')
+            .ok_or_else(|| Error::InvalidShortcut("no # separator".to_string()))?;
+        let codebase = codebase.trim();
+        if codebase.is_empty() {
+            return Err(Error::InvalidShortcut("no deployment URL".to_string()));
+        }
+
+        let mut parts = identity.split(',').map(str::trim);
+        let name = match parts.next() {
+            Some(name) if !name.is_empty() => name,
+            _ => return Err(Error::InvalidShortcut("no deployment name".to_string())),
+        };
+        let mut reference = ApplicationReference {
+            codebase: codebase.to_string(),
+            name: name.to_string(),
+            culture: None,
+            public_key_token: None,
+            processor_architecture: None,
+        };
+        for part in parts {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| Error::InvalidShortcut(format!("malformed attribute {}", part)))?;
+            let value = Some(value.trim().to_string());
+            match key.trim().to_ascii_lowercase().as_str() {
+                "culture" => reference.culture = value,
+                "publickeytoken" => reference.public_key_token = value,
+                "processorarchitecture" => reference.processor_architecture = value,
+                _ => {}
+            }
+        }
+        Ok(reference)
+    }
+}
+
+impl fmt::Display for ApplicationReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.codebase, self.name)?;
+        if let Some(culture) = &self.culture {
+            write!(f, ", Culture={}", culture)?;
+        }
+        if let Some(token) = &self.public_key_token {
+            write!(f, ", PublicKeyToken={}", token)?;
+        }
+        if let Some(architecture) = &self.processor_architecture {
+            write!(f, ", processorArchitecture={}", architecture)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FromXML;
+
+    const SHORTCUT: &str = "http://example.com/deploy/My%20Application%20Deployment.application#My Application Deployment.app, Culture=neutral, PublicKeyToken=43cb1e8e7a352766, processorArchitecture=x86";
+
+    #[test]
+    fn round_trips_utf16_shortcut() {
+        let reference: ApplicationReference = SHORTCUT.parse().unwrap();
+        assert_eq!(
+            reference.codebase,
+            "http://example.com/deploy/My%20Application%20Deployment.application"
+        );
+        assert_eq!(reference.name, "My Application Deployment.app");
+        assert_eq!(reference.culture.as_deref(), Some("neutral"));
+        assert_eq!(
+            reference.public_key_token.as_deref(),
+            Some("43cb1e8e7a352766")
+        );
+        assert_eq!(reference.processor_architecture.as_deref(), Some("x86"));
+
+        let bytes = reference.to_bytes();
+        assert_eq!(&bytes[..2], &[0xff, 0xfe]);
+        assert_eq!(ApplicationReference::from_bytes(&bytes).unwrap(), reference);
+        assert_eq!(reference.to_string(), SHORTCUT);
+    }
+
+    #[test]
+    fn rejects_malformed_shortcuts() {
+        assert!("http://example.com/App.application"
+            .parse::<ApplicationReference>()
+            .is_err());
+        assert!("#App.application".parse::<ApplicationReference>().is_err());
+        assert!("http://example.com/App.application#, Culture=neutral"
+            .parse::<ApplicationReference>()
+            .is_err());
+    }
+
+    #[test]
+    fn resolves_to_deployment_provider() {
+        let manifest =
+            DeploymentManifest::from_xml_bytes(include_bytes!("example/sample.application"))
+                .unwrap();
+        let reference: ApplicationReference = SHORTCUT.parse().unwrap();
+        assert_eq!(
+            reference.resolve(&manifest).unwrap(),
+            r"\\myServer\sampleDeployment\MyApplicationDeployment.application"
+        );
+
+        let other = ApplicationReference {
+            public_key_token: Some("0000000000000000".to_string()),
+            ..reference
+        };
+        assert!(other.resolve(&manifest).is_err());
+    }
+}
@@ -0,0 +1,938 @@
+//! Models for bootstrapper package manifests
+//!
+//! The ClickOnce bootstrapper (`setup.exe`) installs an application's
+//! prerequisites before the application itself. Each prerequisite is
+//! described by a `product.xml` file, with a `package.xml` file per culture
+//! holding its localized strings and license.
+//!
+//! Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/product-and-package-schema-reference?view=vs-2022
+use crate::FromXML;
+use serde::Deserialize;
+
+/// Model for <Product> element, the root of `product.xml`
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/product-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Product {
+    /// Attribute. Required. The unique identifier for the package.
+    #[serde(alias = "ProductCode")]
+    pub product_code: String,
+
+    /// Child. Optional. Products that this product depends on or includes.
+    #[serde(alias = "RelatedProducts")]
+    pub related_products: Option<RelatedProducts>,
+
+    /// Child. Optional. The files that the installation process may install.
+    #[serde(alias = "PackageFiles")]
+    pub package_files: Option<PackageFiles>,
+
+    /// Child. Optional. Tests to perform on the local computer before installation.
+    #[serde(alias = "InstallChecks")]
+    pub install_checks: Option<InstallChecks>,
+
+    /// Child. Optional. Commands that install the product.
+    #[serde(alias = "Commands")]
+    pub commands: Option<Commands>,
+}
+
+impl FromXML<'_> for Product {}
+
+impl Product {
+    /// The assemblies the product checks for in the global assembly cache,
+    /// which can be matched against the prerequisite `<dependentAssembly>`
+    /// entries of an application manifest.
+    pub fn assembly_checks(&self) -> impl Iterator<Item = &AssemblyCheck> {
+        self.install_checks
+            .iter()
+            .flat_map(|checks| checks.checks.iter())
+            .filter_map(|check| match check {
+                InstallCheck::AssemblyCheck(check) => Some(check),
+                _ => None,
+            })
+    }
+}
+
+/// Model for <Package> element, the root of `package.xml`
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/package-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Package {
+    /// Attribute. Required. The name of the package, as a reference into `strings`.
+    #[serde(alias = "Name")]
+    pub name: String,
+
+    /// Attribute. Required. The culture of the package, as a reference into `strings`.
+    #[serde(alias = "Culture")]
+    pub culture: String,
+
+    /// Attribute. Optional. The name of a package file containing the end-user license agreement.
+    #[serde(alias = "LicenseAgreement")]
+    pub license_agreement: Option<String>,
+
+    /// Child. Optional. The files that the installation process may install.
+    #[serde(alias = "PackageFiles")]
+    pub package_files: Option<PackageFiles>,
+
+    /// Child. Optional. Tests to perform on the local computer before installation.
+    #[serde(alias = "InstallChecks")]
+    pub install_checks: Option<InstallChecks>,
+
+    /// Child. Optional. Commands that install the package.
+    #[serde(alias = "Commands")]
+    pub commands: Option<Commands>,
+
+    /// Child. Optional. Localized strings for the product name and error messages.
+    #[serde(alias = "Strings")]
+    pub strings: Option<Strings>,
+}
+
+impl FromXML<'_> for Package {}
+
+impl Package {
+    /// Looks up a localized string by name.
+    pub fn string(&self, name: &str) -> Option<&str> {
+        self.strings
+            .as_ref()?
+            .string
+            .iter()
+            .find(|string| string.name == name)
+            .map(|string| string.value.as_str())
+    }
+
+    /// The localized display name of the package.
+    pub fn display_name(&self) -> Option<&str> {
+        self.string(&self.name)
+    }
+}
+
+/// Model for <RelatedProducts> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/relatedproducts-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct RelatedProducts {
+    /// Children. Optional. The related products, in document order.
+    #[serde(rename(deserialize = "$value"), default)]
+    #[cfg_attr(feature = "schema", schemars(rename = "products"))]
+    pub products: Vec<RelatedProduct>,
+}
+
+/// Children of <RelatedProducts>
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub enum RelatedProduct {
+    /// A product that must be installed before this one.
+    DependsOnProduct(ProductReference),
+
+    /// A set of products, at least one of which must be installed before this one.
+    EitherProducts(EitherProducts),
+
+    /// A product that this one installs, so need not be installed separately.
+    IncludesProduct(ProductReference),
+}
+
+/// Model for <DependsOnProduct> and <IncludesProduct> elements
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct ProductReference {
+    /// Attribute. Required. The `ProductCode` of the referenced product.
+    #[serde(alias = "Code")]
+    pub code: String,
+}
+
+/// Model for <EitherProducts> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct EitherProducts {
+    /// Children. Required. The alternative products.
+    #[serde(rename(deserialize = "$value"), default)]
+    #[cfg_attr(feature = "schema", schemars(rename = "dependsOnProduct"))]
+    pub depends_on_product: Vec<ProductReference>,
+}
+
+/// Model for <PackageFiles> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/packagefiles-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct PackageFiles {
+    /// Attribute. Optional. If false, only files referenced by a command are copied.
+    #[serde(alias = "CopyAllPackageFiles")]
+    pub copy_all_package_files: Option<String>,
+
+    /// Children. Optional. The package files.
+    #[serde(alias = "PackageFile", default)]
+    pub package_file: Vec<PackageFile>,
+}
+
+/// Model for <PackageFile> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/packagefile-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct PackageFile {
+    /// Attribute. Required. The name of the file, relative to the package directory.
+    #[serde(alias = "Name")]
+    pub name: String,
+
+    /// Attribute. Optional. A reference into the package strings giving a URL to download the file from.
+    #[serde(alias = "HomeSite")]
+    pub home_site: Option<String>,
+
+    /// Attribute. Optional. The public key of the file's Authenticode signer, as hex.
+    #[serde(alias = "PublicKey")]
+    pub public_key: Option<String>,
+
+    /// Attribute. Optional. The SHA-1 hash of the file, as hex.
+    #[serde(alias = "Hash")]
+    pub hash: Option<String>,
+
+    /// Attribute. Optional. If true, the file is copied to the deployment even when `HomeSite` is set.
+    #[serde(alias = "CopyOnBuild")]
+    pub copy_on_build: Option<bool>,
+}
+
+/// Model for <InstallChecks> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/installchecks-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct InstallChecks {
+    /// Children. Optional. The checks, in document order.
+    #[serde(rename(deserialize = "$value"), default)]
+    #[cfg_attr(feature = "schema", schemars(rename = "checks"))]
+    pub checks: Vec<InstallCheck>,
+}
+
+/// A test performed on the local computer, whose result is stored in a
+/// property for use by install conditions
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub enum InstallCheck {
+    /// Checks whether an assembly is in the global assembly cache.
+    AssemblyCheck(AssemblyCheck),
+
+    /// Runs an executable from the package and stores its exit code.
+    ExternalCheck(ExternalCheck),
+
+    /// Checks whether a file exists, and stores its version.
+    FileCheck(FileCheck),
+
+    /// Checks whether a Windows Installer product or feature is installed.
+    MsiProductCheck(MsiProductCheck),
+
+    /// Reads a registry value.
+    RegistryCheck(RegistryCheck),
+
+    /// Checks whether a file named by a registry value exists.
+    RegistryFileCheck(RegistryFileCheck),
+}
+
+impl InstallCheck {
+    /// The property that the check stores its result in.
+    pub fn property(&self) -> &str {
+        match self {
+            InstallCheck::AssemblyCheck(check) => &check.property,
+            InstallCheck::ExternalCheck(check) => &check.property,
+            InstallCheck::FileCheck(check) => &check.property,
+            InstallCheck::MsiProductCheck(check) => &check.property,
+            InstallCheck::RegistryCheck(check) => &check.property,
+            InstallCheck::RegistryFileCheck(check) => &check.property,
+        }
+    }
+}
+
+/// Model for <AssemblyCheck> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct AssemblyCheck {
+    /// Attribute. Required. The property to store the result in.
+    #[serde(alias = "Property")]
+    pub property: String,
+
+    /// Attribute. Required. The name of the assembly.
+    #[serde(alias = "Name")]
+    pub name: String,
+
+    /// Attribute. Required. The public key token of the assembly.
+    #[serde(alias = "PublicKeyToken")]
+    pub public_key_token: String,
+
+    /// Attribute. Required. The version of the assembly.
+    #[serde(alias = "Version")]
+    pub version: String,
+
+    /// Attribute. Optional. The language of the assembly.
+    #[serde(alias = "Language")]
+    pub language: Option<String>,
+
+    /// Attribute. Optional. The processor architecture of the assembly.
+    #[serde(alias = "ProcessorArchitecture")]
+    pub processor_architecture: Option<String>,
+}
+
+/// Model for <ExternalCheck> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct ExternalCheck {
+    /// Attribute. Required. The property to store the exit code in.
+    #[serde(alias = "Property")]
+    pub property: String,
+
+    /// Attribute. Required. The package file to run.
+    #[serde(alias = "PackageFile")]
+    pub package_file: String,
+
+    /// Attribute. Optional. Command line arguments for the package file.
+    #[serde(alias = "Arguments")]
+    pub arguments: Option<String>,
+}
+
+/// Model for <FileCheck> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct FileCheck {
+    /// Attribute. Required. The property to store the file version in.
+    #[serde(alias = "Property")]
+    pub property: String,
+
+    /// Attribute. Required. The name of the file to look for.
+    #[serde(alias = "FileName")]
+    pub file_name: String,
+
+    /// Attribute. Optional. The directory to search in.
+    #[serde(alias = "SearchPath")]
+    pub search_path: Option<String>,
+
+    /// Attribute. Optional. A special folder, such as `WindowsFolder`, to search in.
+    #[serde(alias = "SpecialFolder")]
+    pub special_folder: Option<String>,
+
+    /// Attribute. Optional. How many levels of subdirectories to search.
+    #[serde(alias = "SearchDepth")]
+    pub search_depth: Option<u32>,
+}
+
+/// Model for <MsiProductCheck> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct MsiProductCheck {
+    /// Attribute. Required. The property to store the install state in.
+    #[serde(alias = "Property")]
+    pub property: String,
+
+    /// Attribute. Required. The product GUID.
+    #[serde(alias = "Product")]
+    pub product: String,
+
+    /// Attribute. Optional. The feature to check for.
+    #[serde(alias = "Feature")]
+    pub feature: Option<String>,
+}
+
+/// Model for <RegistryCheck> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct RegistryCheck {
+    /// Attribute. Required. The property to store the value in.
+    #[serde(alias = "Property")]
+    pub property: String,
+
+    /// Attribute. Required. The registry key.
+    #[serde(alias = "Key")]
+    pub key: String,
+
+    /// Attribute. Optional. The registry value, or the key's default value if omitted.
+    #[serde(alias = "Value")]
+    pub value: Option<String>,
+}
+
+/// Model for <RegistryFileCheck> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct RegistryFileCheck {
+    /// Attribute. Required. The property to store the file version in.
+    #[serde(alias = "Property")]
+    pub property: String,
+
+    /// Attribute. Required. The registry key holding the file path.
+    #[serde(alias = "Key")]
+    pub key: String,
+
+    /// Attribute. Optional. The registry value holding the file path.
+    #[serde(alias = "Value")]
+    pub value: Option<String>,
+
+    /// Attribute. Optional. The name of the file, if the registry holds a directory.
+    #[serde(alias = "FileName")]
+    pub file_name: Option<String>,
+
+    /// Attribute. Optional. How many levels of subdirectories to search.
+    #[serde(alias = "SearchDepth")]
+    pub search_depth: Option<u32>,
+}
+
+/// Model for <Commands> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/commands-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Commands {
+    /// Attribute. Optional. When to reboot if a command requests it.
+    #[serde(alias = "Reboot")]
+    pub reboot: Option<Reboot>,
+
+    /// Children. Required. The commands, tried in order until one is not bypassed.
+    #[serde(alias = "Command", default)]
+    pub command: Vec<Command>,
+}
+
+/// Values of the `Reboot` attribute of <Commands>
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub enum Reboot {
+    /// Reboot once all packages are installed.
+    Defer,
+    /// Reboot as soon as the command requests it.
+    Immediate,
+    /// Never reboot.
+    None,
+}
+
+/// Model for <Command> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Command {
+    /// Attribute. Required. The package file to run.
+    #[serde(alias = "PackageFile")]
+    pub package_file: String,
+
+    /// Attribute. Optional. Command line arguments for the package file.
+    #[serde(alias = "Arguments")]
+    pub arguments: Option<String>,
+
+    /// Attribute. Optional. The disk space the installed package uses.
+    #[serde(alias = "EstimatedInstalledBytes")]
+    pub estimated_installed_bytes: Option<u64>,
+
+    /// Attribute. Optional. The temporary disk space the installation uses.
+    #[serde(alias = "EstimatedTempBytes")]
+    pub estimated_temp_bytes: Option<u64>,
+
+    /// Attribute. Optional. How long the installation takes.
+    #[serde(alias = "EstimatedInstallSeconds")]
+    pub estimated_install_seconds: Option<u64>,
+
+    /// Child. Optional. Conditions under which the command is skipped or fails.
+    #[serde(alias = "InstallConditions")]
+    pub install_conditions: Option<InstallConditions>,
+
+    /// Child. Optional. How the command's exit codes are interpreted.
+    #[serde(alias = "ExitCodes")]
+    pub exit_codes: Option<ExitCodes>,
+}
+
+/// Model for <InstallConditions> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/installconditions-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct InstallConditions {
+    /// Children. Optional. The conditions, in document order.
+    #[serde(rename(deserialize = "$value"), default)]
+    #[cfg_attr(feature = "schema", schemars(rename = "conditions"))]
+    pub conditions: Vec<InstallCondition>,
+}
+
+/// Children of <InstallConditions>
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub enum InstallCondition {
+    /// Skip the command if the condition holds.
+    BypassIf(Condition),
+    /// Abort the installation if the condition holds.
+    FailIf(Condition),
+}
+
+/// Model for <BypassIf> and <FailIf> elements
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Condition {
+    /// Attribute. Required. The property to test.
+    #[serde(alias = "Property")]
+    pub property: String,
+
+    /// Attribute. Required. How to compare the property with `value`.
+    #[serde(alias = "Compare")]
+    pub compare: Compare,
+
+    /// Attribute. Optional. The value to compare against.
+    #[serde(alias = "Value")]
+    pub value: Option<String>,
+
+    /// Attribute. Optional. For <FailIf>, a reference into the package strings for the error message.
+    #[serde(alias = "String")]
+    pub string: Option<String>,
+}
+
+/// Comparisons available to install conditions
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub enum Compare {
+    /// The property equals the value.
+    ValueEqualTo,
+    /// The property does not equal the value.
+    ValueNotEqualTo,
+    /// The property is greater than the value.
+    ValueGreaterThan,
+    /// The property is greater than or equal to the value.
+    ValueGreaterThanOrEqualTo,
+    /// The property is less than the value.
+    ValueLessThan,
+    /// The property is less than or equal to the value.
+    ValueLessThanOrEqualTo,
+    /// The property is set.
+    ValueExists,
+    /// The property is not set.
+    ValueNotExists,
+    /// The property, as a version, equals the value.
+    VersionEqualTo,
+    /// The property, as a version, does not equal the value.
+    VersionNotEqualTo,
+    /// The property, as a version, is greater than the value.
+    VersionGreaterThan,
+    /// The property, as a version, is greater than or equal to the value.
+    VersionGreaterThanOrEqualTo,
+    /// The property, as a version, is less than the value.
+    VersionLessThan,
+    /// The property, as a version, is less than or equal to the value.
+    VersionLessThanOrEqualTo,
+}
+
+/// Model for <ExitCodes> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/exitcodes-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct ExitCodes {
+    /// Children. Optional. The results of specific exit codes.
+    #[serde(alias = "ExitCode", default)]
+    pub exit_code: Vec<ExitCode>,
+
+    /// Child. Optional. The result of any exit code not listed.
+    #[serde(alias = "DefaultExitCode")]
+    pub default_exit_code: Option<DefaultExitCode>,
+}
+
+impl ExitCodes {
+    /// The result of a command that exited with `code`.
+    pub fn result(&self, code: i64) -> Option<&ExitResult> {
+        self.exit_code
+            .iter()
+            .find(|exit_code| exit_code.value == code)
+            .map(|exit_code| &exit_code.result)
+            .or_else(|| {
+                self.default_exit_code
+                    .as_ref()
+                    .map(|default| &default.result)
+            })
+    }
+}
+
+/// Model for <ExitCode> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct ExitCode {
+    /// Attribute. Required. The exit code.
+    #[serde(alias = "Value")]
+    pub value: i64,
+
+    /// Attribute. Required. How the installation treats the exit code.
+    #[serde(alias = "Result")]
+    pub result: ExitResult,
+
+    /// Attribute. Optional. A reference into the package strings for the message to show.
+    #[serde(alias = "String")]
+    pub string: Option<String>,
+
+    /// Attribute. Optional. If true, the system message for the exit code is shown.
+    #[serde(alias = "FormatMessageFromSystem")]
+    pub format_message_from_system: Option<bool>,
+}
+
+/// Model for <DefaultExitCode> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct DefaultExitCode {
+    /// Attribute. Required. How the installation treats the exit code.
+    #[serde(alias = "Result")]
+    pub result: ExitResult,
+
+    /// Attribute. Optional. A reference into the package strings for the message to show.
+    #[serde(alias = "String")]
+    pub string: Option<String>,
+
+    /// Attribute. Optional. If true, the system message for the exit code is shown.
+    #[serde(alias = "FormatMessageFromSystem")]
+    pub format_message_from_system: Option<bool>,
+}
+
+/// How the bootstrapper treats a command's exit code
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub enum ExitResult {
+    /// The package installed.
+    Success,
+    /// The package installed, and the computer must be restarted.
+    SuccessReboot,
+    /// The package failed to install.
+    Fail,
+    /// The package failed to install, and the computer must be restarted.
+    FailReboot,
+}
+
+/// Model for <Strings> element
+///
+/// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/strings-element-bootstrapper?view=vs-2022
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Strings {
+    /// Children. Optional. The localized strings.
+    #[serde(alias = "String", default)]
+    pub string: Vec<LocalizedString>,
+}
+
+/// Model for <String> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct LocalizedString {
+    /// Attribute. Required. The name that other elements refer to the string by.
+    #[serde(alias = "Name")]
+    pub name: String,
+
+    /// The text of the string.
+    #[serde(rename(deserialize = "$value"), default)]
+    #[cfg_attr(feature = "schema", schemars(rename = "value"))]
+    pub value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_product() {
+        let product = Product::from_xml_bytes(include_bytes!("example/bootstrapper/product.xml"))
+            .expect("Failure parsing product");
+        assert_eq!(product.product_code, ".NETFramework,Version=v4.8");
+        assert_eq!(product.related_products.as_ref().unwrap().products.len(), 2);
+        assert_eq!(
+            product.package_files.as_ref().unwrap().package_file.len(),
+            2
+        );
+
+        let checks = &product.install_checks.as_ref().unwrap().checks;
+        let properties: Vec<_> = checks.iter().map(InstallCheck::property).collect();
+        assert_eq!(properties, ["DotNet48Check", "Release", "CLRInstalled"]);
+        assert_eq!(
+            product
+                .assembly_checks()
+                .map(|check| check.name.as_str())
+                .collect::<Vec<_>>(),
+            ["mscorlib"]
+        );
+
+        let commands = product.commands.unwrap();
+        assert_eq!(commands.reboot, Some(Reboot::Defer));
+        let command = &commands.command[0];
+        assert_eq!(command.estimated_installed_bytes, Some(437362964));
+        let conditions = &command.install_conditions.as_ref().unwrap().conditions;
+        assert_eq!(conditions.len(), 3);
+        assert!(matches!(
+            &conditions[2],
+            InstallCondition::BypassIf(Condition {
+                compare: Compare::ValueGreaterThanOrEqualTo,
+                ..
+            })
+        ));
+
+        let exit_codes = command.exit_codes.as_ref().unwrap();
+        assert_eq!(exit_codes.result(3010), Some(&ExitResult::SuccessReboot));
+        assert_eq!(exit_codes.result(1603), Some(&ExitResult::Fail));
+    }
+
+    #[test]
+    fn parse_package() {
+        let package = Package::from_xml_bytes(include_bytes!("example/bootstrapper/package.xml"))
+            .expect("Failure parsing package");
+        assert_eq!(
+            package.display_name(),
+            Some("Microsoft .NET Framework 4.8 (x86 and x64)")
+        );
+        assert_eq!(package.string("Culture"), Some("en"));
+        assert_eq!(package.license_agreement.as_deref(), Some("eula.rtf"));
+    }
+}
@@ -0,0 +1,196 @@
+//! X.509 certificates embedded in manifest signatures
+//!
+//! Names are formatted the way Windows formats them in `publisherIdentity`:
+//! most specific component first, with `S=` for the state or province.
+use crate::Error;
+use sha1::{Digest, Sha1};
+use time::OffsetDateTime;
+use x509_parser::prelude::{FromDer, X509Certificate, X509Name};
+
+/// A parsed X.509 certificate
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    /// The DER encoding of the certificate
+    pub der: Vec<u8>,
+    /// Distinguished name of the certificate's subject
+    pub subject: String,
+    /// Distinguished name of the certificate's issuer
+    pub issuer: String,
+    /// Serial number, as uppercase hexadecimal
+    pub serial_number: String,
+    /// Start of the validity window
+    pub not_before: OffsetDateTime,
+    /// End of the validity window
+    pub not_after: OffsetDateTime,
+    /// SHA-1 hash of the certificate, as uppercase hexadecimal
+    pub thumbprint: String,
+    /// The subject's public key, as stored in the certificate
+    pub public_key: Vec<u8>,
+}
+
+/// Formats bytes as hexadecimal.
+pub(crate) fn to_hex(bytes: &[u8], upper: bool) -> String {
+    bytes
+        .iter()
+        .map(|b| {
+            if upper {
+                format!("{:02X}", b)
+            } else {
+                format!("{:02x}", b)
+            }
+        })
+        .collect()
+}
+
+/// Formats the content octets of a DER integer serial number as uppercase
+/// hexadecimal, without the zero byte DER adds to keep it positive.
+pub(crate) fn serial_hex(serial: &[u8]) -> String {
+    let serial = match serial {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => serial,
+    };
+    to_hex(serial, true)
+}
+
+fn attribute_name(oid: &str) -> String {
+    match oid {
+        "2.5.4.3" => "CN".to_string(),
+        "2.5.4.4" => "SN".to_string(),
+        "2.5.4.5" => "SERIALNUMBER".to_string(),
+        "2.5.4.6" => "C".to_string(),
+        "2.5.4.7" => "L".to_string(),
+        "2.5.4.8" => "S".to_string(),
+        "2.5.4.9" => "STREET".to_string(),
+        "2.5.4.10" => "O".to_string(),
+        "2.5.4.11" => "OU".to_string(),
+        "2.5.4.12" => "T".to_string(),
+        "2.5.4.42" => "G".to_string(),
+        "1.2.840.113549.1.9.1" => "E".to_string(),
+        "0.9.2342.19200300.100.1.25" => "DC".to_string(),
+        _ => format!("OID.{}", oid),
+    }
+}
+
+fn quote_value(value: &str) -> String {
+    if value.contains([',', '+', '=', '"', '<', '>', '#', ';'])
+        || value.starts_with(' ')
+        || value.ends_with(' ')
+    {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Formats a distinguished name as Windows does.
+pub(crate) fn format_name(name: &X509Name) -> String {
+    let rdns: Vec<String> =
+        name.iter()
+            .map(|rdn| {
+                rdn.iter()
+                    .map(|attribute| {
+                        let value = attribute.as_str().map(quote_value).unwrap_or_else(|_| {
+                            format!("#{}", to_hex(attribute.as_slice(), false))
+                        });
+                        format!(
+                            "{}={}",
+                            attribute_name(&attribute.attr_type().to_id_string()),
+                            value
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" + ")
+            })
+            .collect();
+    rdns.into_iter().rev().collect::<Vec<_>>().join(", ")
+}
+
+fn name_components(name: &str) -> Vec<String> {
+    let mut components = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in name.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' | ';' if !quoted => components.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    components.push(current);
+    components
+        .iter()
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// Compares two distinguished names, ignoring case and the spacing between
+/// components.
+pub fn names_match(a: &str, b: &str) -> bool {
+    name_components(a) == name_components(b)
+}
+
+impl Certificate {
+    /// Parses a DER encoded certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        let (_, certificate) = X509Certificate::from_der(der)
+            .map_err(|e| Error::Certificate(format!("invalid certificate: {}", e)))?;
+
+        Ok(Self {
+            der: der.to_vec(),
+            subject: format_name(certificate.subject()),
+            issuer: format_name(certificate.issuer()),
+            serial_number: serial_hex(certificate.raw_serial()),
+            not_before: certificate.validity().not_before.to_datetime(),
+            not_after: certificate.validity().not_after.to_datetime(),
+            thumbprint: to_hex(&Sha1::digest(der), true),
+            public_key: certificate.public_key().subject_public_key.data.to_vec(),
+        })
+    }
+
+    /// SHA-1 hash of the public key, as lowercase hexadecimal. This is the
+    /// form used by the `issuerKeyHash` attribute of `publisherIdentity`.
+    pub fn public_key_hash(&self) -> String {
+        to_hex(&Sha1::digest(&self.public_key), false)
+    }
+
+    /// Whether the certificate was issued by its own subject.
+    pub fn is_self_issued(&self) -> bool {
+        self.subject == self.issuer
+    }
+
+    /// Whether `time` falls within the certificate's validity window.
+    pub fn is_valid_at(&self, time: OffsetDateTime) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_serial_numbers() {
+        assert_eq!(serial_hex(&[0x00, 0x8A, 0x01]), "8A01");
+        assert_eq!(serial_hex(&[0x1A, 0x00]), "1A00");
+        assert_eq!(serial_hex(&[0x00]), "00");
+        // Only the sign byte is dropped, not every leading zero
+        assert_eq!(serial_hex(&[0x00, 0x00, 0x1A]), "001A");
+    }
+
+    #[test]
+    fn compares_names() {
+        assert!(names_match(
+            "CN=Example Corp, O=Example Corp, C=US",
+            "cn=example corp,o=Example Corp,  C=US"
+        ));
+        assert!(names_match("CN=\"A, B\", C=US", "CN=\"A, B\",C=US"));
+        assert!(!names_match(
+            "CN=Example Corp, C=US",
+            "CN=Example Corp, C=GB"
+        ));
+    }
+}
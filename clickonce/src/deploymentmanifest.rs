@@ -1,5 +1,7 @@
 //! Models for ClickOnce deployment manifest
 //!
+use crate::digest::DigestAlgorithm;
+use crate::signature::Signature;
 use crate::FromXML;
 use serde::Deserialize;
 
@@ -15,6 +17,16 @@ fn default_false() -> bool {
 ///
 /// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/clickonce-deployment-manifest?view=vs-2022
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 #[serde(transparent)]
 pub struct DeploymentManifest {
     /// Required. The top-level element for the deployment manifest.
@@ -27,6 +39,16 @@ impl FromXML<'_> for DeploymentManifest {}
 ///
 /// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/assembly-element-clickonce-deployment?view=vs-2022
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct Assembly {
     /// Attribute. Required. This attribute must be set to 1.0.
     #[serde(alias = "manifestVersion")]
@@ -48,12 +70,30 @@ pub struct Assembly {
 
     /// Child. Required. Identifies the version of the application to install for the deployment and the location of the application manifest.
     pub dependency: Vec<Dependency>,
+
+    /// Child. Required for signed manifests. Identifies the publisher that signed the manifest.
+    #[serde(alias = "publisherIdentity")]
+    pub publisher_identity: Option<PublisherIdentity>,
+
+    /// Child. Required for signed manifests. The XML digital signature over the manifest.
+    #[serde(alias = "Signature")]
+    pub signature: Option<Signature>,
 }
 
 /// Model for <assemblyIdentity> element
 ///
 /// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/assemblyidentity-element-clickonce-deployment?view=vs-2022
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct AssemblyIdentity {
     /// Attribute. Required. Identifies the human-readable name of the deployment for informational purposes.
     ///
@@ -83,6 +123,16 @@ pub struct AssemblyIdentity {
 
 /// Types of permitted processor architecture
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "lowercase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "lowercase")
+)]
 pub enum ProcessorArchitecture {
     /// All processors
     #[serde(alias = "msil")]
@@ -100,6 +150,16 @@ pub enum ProcessorArchitecture {
 ///
 /// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/description-element-clickonce-deployment?view=vs-2022
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct Description {
     /// Attribute. Required. Identifies the company name used for icon placement in the Windows Start menu and the Add or Remove Programs item in Control Panel, when the deployment is configured for install.
     pub publisher: String,
@@ -120,6 +180,16 @@ pub struct Description {
 ///
 /// Defined at https://docs.microsoft.com/en-us/visualstudio/deployment/deployment-element-clickonce-deployment?view=vs-2022
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct Deployment {
     /// Attribute. Required. Specifies whether this application defines a presence on the Windows Start menu
     /// and in the Control Panel Add or Remove Programs application. Valid values are true and false. If
@@ -179,6 +249,16 @@ pub struct Deployment {
 /// install attribute of the deployment element is false, the subscription element is ignored, because
 /// a ClickOnce application that is launched from the network always uses the latest version.
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct Subscription {
     /// Required. This element is a child of the subscription element and contains either the
     /// beforeApplicationStartup or the expiration element. beforeApplicationStartup and expiration
@@ -190,6 +270,16 @@ pub struct Subscription {
 /// beforeApplicationStartup or the expiration element. beforeApplicationStartup and expiration cannot
 /// both be specified in the same deployment manifest.
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub enum Update {
     /// No child found
     None,
@@ -209,6 +299,16 @@ pub enum Update {
 
 /// Optional. This element is a child of the update element, and has no children. beforeApplicationStartup and expiration cannot both be specified in the same deployment manifest. When the update check occurs and an updated version is detected, the new version caches while the existing version runs. The new version then installs on the next launch of the ClickOnce application.
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct Expiration {
     /// Attribute. Required. Identifies how old the current update should become before the application performs an update check. The unit of time is determined by the unit attribute.
     #[serde(alias = "maximumAge")]
@@ -219,6 +319,16 @@ pub struct Expiration {
 
 /// For the .NET Framework 2.0, this element is required if the deployment manifest contains a subscription section. For the .NET Framework 3.5 and later, this element is optional, and will default to the server and file path in which the deployment manifest was discovered.
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct DeploymentProvider {
     /// Attribute. Required. Identifies the location, as a Uniform Resource Identifier (URI), of the deployment manifest that is used to update the ClickOnce application. This element also allows for forwarding update locations for CD-based installations. Must be a valid URI.
     pub codebase: String,
@@ -230,6 +340,16 @@ pub struct DeploymentProvider {
 ///
 /// The compatibleFrameworks element is required for deployment manifests that target the ClickOnce runtime provided by .NET Framework 4 or later. The compatibleFrameworks element contains one or more framework elements that specify the .NET Framework versions on which this application can run. The ClickOnce runtime will run the application on the first available framework in this list.
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct CompatibleFrameworks {
     /// Attribute. Optional. Specifies a URL where the preferred compatible .NET Framework version can be downloaded.
     pub support_url: Option<String>,
@@ -241,6 +361,16 @@ pub struct CompatibleFrameworks {
 
 /// Structure covering the Framework
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct Framework {
     /// Attribute. Required. Specifies the version number of the target .NET Framework.
     #[serde(alias = "targetVersion")]
@@ -258,6 +388,16 @@ pub struct Framework {
 ///
 /// Identifies the version of the application to install for the deployment and the location of the application manifest.
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct Dependency {
     /// Child. Parent of assemblyIdentity.
     #[serde(alias = "dependentAssembly")]
@@ -266,6 +406,16 @@ pub struct Dependency {
 
 /// Parent of assemblyIdentity.
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct DependentAssembly {
     /// Attribute. Optional. Specifies that this assembly should already exist in the GAC. Valid values are true and false. If true, and the specified assembly does not exist in the GAC, the application fails to run.
     #[serde(alias = "preRequisite")]
@@ -285,7 +435,7 @@ pub struct DependentAssembly {
     pub codebase: Option<String>,
 
     /// Attribute. Optional. The size of the application manifest, in bytes.
-    pub size: u32,
+    pub size: u64,
 
     /// Child. Required. Content should be the same as the application manifest.
     #[serde(alias = "assemblyIdentity")]
@@ -297,6 +447,16 @@ pub struct DependentAssembly {
 
 /// Types for the relationship between a dependency and the application
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub enum DependencyType {
     /// Component represents a separate installation from the current application.
     #[serde(alias = "install")]
@@ -309,6 +469,16 @@ pub enum DependencyType {
 
 /// Model for <hash>
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct Hash {
     /// Child. Required. Parent of dsig:Transform
     #[serde(alias = "Transforms")]
@@ -322,8 +492,36 @@ pub struct Hash {
     #[serde(alias = "DigestValue")]
     pub dsig_digest_value: DsigDigestValue,
 }
+
+impl Hash {
+    /// The digest algorithm named by `<DigestMethod>`, if it is supported.
+    pub fn algorithm(&self) -> Option<DigestAlgorithm> {
+        DigestAlgorithm::from_uri(&self.dsig_digest_method.algorithm)
+    }
+
+    /// Whether `digest` is the digest recorded in `<DigestValue>`.
+    pub fn matches(&self, digest: &[u8]) -> bool {
+        let compact: String = self
+            .dsig_digest_value
+            .value
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        base64::decode(compact).is_ok_and(|expected| expected == digest)
+    }
+}
 /// Model for <dsig:Transforms>
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct DsigTransforms {
     /// Child. Required. dsig:Transform
     #[serde(alias = "Transform")]
@@ -331,6 +529,16 @@ pub struct DsigTransforms {
 }
 /// Model for <dsig:Transform>
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct DsigTransform {
     /// Attribute. Required. The algorithm used to calculate the digest for this file. Currently the only value used by ClickOnce is urn:schemas-microsoft-com:HashTransforms.Identity.
     #[serde(alias = "Algorithm")]
@@ -338,6 +546,16 @@ pub struct DsigTransform {
 }
 /// Model for <dsig:DigestMethod>
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct DsigDigestMethod {
     /// Attribute. Required. The algorithm used to calculate the digest for this file. Currently the only value used by ClickOnce is http://www.w3.org/2000/09/xmldsig#sha1.
     #[serde(alias = "Algorithm")]
@@ -345,28 +563,95 @@ pub struct DsigDigestMethod {
 }
 /// Model for <dsig:DigestValue>
 #[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
 pub struct DsigDigestValue {
     /// Text. Required.
-    #[serde(rename = "$value")]
+    #[serde(rename(deserialize = "$value"))]
+    #[cfg_attr(feature = "schema", schemars(rename = "value"))]
     pub value: String,
 }
 
-/// TODO: Parse the publisherIdentity, Signature and customErrorReporting elements
+/// Model for <publisherIdentity> element
+///
+/// Identifies the publisher whose Authenticode certificate signed the manifest.
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct PublisherIdentity {
+    /// Attribute. Required. The distinguished name of the signing certificate's subject.
+    pub name: String,
+
+    /// Attribute. Required. The SHA-1 hash of the public key of the signing certificate's issuer.
+    #[serde(alias = "issuerKeyHash")]
+    pub issuer_key_hash: String,
+}
+
+// TODO: Parse the customErrorReporting element
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn deployment_manifest() -> &'static str {
-        include_str!("example/sample.application").trim_start_matches('\u{feff}')
+    fn deployment_manifest() -> &'static [u8] {
+        include_bytes!("example/sample.application")
     }
 
     #[test]
     fn parse_manifest() {
-        let manifest_contents = deployment_manifest();
+        let manifest_contents =
+            include_str!("example/sample.application").trim_start_matches('\u{feff}');
         let manifest =
             DeploymentManifest::from_xml(manifest_contents).expect("Failure parsing manifest");
         println!("Manifest: {:#?}", manifest);
         assert_eq!(manifest.assembly.assembly_identity.r#type, "win32");
     }
+
+    #[test]
+    fn parse_manifest_bytes() {
+        let manifest = DeploymentManifest::from_xml_bytes(deployment_manifest())
+            .expect("Failure parsing manifest");
+        assert_eq!(manifest.assembly.assembly_identity.r#type, "win32");
+        let publisher = manifest.assembly.publisher_identity.expect("No publisher");
+        assert_eq!(
+            publisher.name,
+            "CN=Example Corp, O=Example Corp, L=Renton, S=Washington, C=US"
+        );
+    }
+
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn serialize_manifest() {
+        let manifest = DeploymentManifest::from_xml_bytes(deployment_manifest())
+            .expect("Failure parsing manifest");
+        let json = serde_json::to_value(&manifest).expect("Failure serializing manifest");
+        let identity = &json["assemblyIdentity"];
+        assert_eq!(identity["publicKeyToken"], "43cb1e8e7a352766");
+        assert_eq!(identity["processorArchitecture"], "x86");
+        assert_eq!(json["manifestVersion"], "1.0");
+        assert_eq!(
+            json["dependency"][0]["dependentAssembly"]["dependencyType"],
+            "install"
+        );
+        assert!(
+            json["dependency"][0]["dependentAssembly"]["hash"]["dsigDigestValue"]["value"]
+                .is_string()
+        );
+    }
 }
@@ -0,0 +1,145 @@
+//! Digest algorithms used by manifest hashes and signatures
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+
+/// A digest algorithm supported by ClickOnce
+///
+/// Manifests built for .NET 4.5 and later use SHA-256; older manifests use
+/// SHA-1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DigestAlgorithm {
+    /// SHA-1
+    Sha1,
+    /// SHA-256
+    #[default]
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// Looks up the algorithm named by a `<DigestMethod>` URI.
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        match uri {
+            "http://www.w3.org/2000/09/xmldsig#sha1" => Some(DigestAlgorithm::Sha1),
+            "http://www.w3.org/2001/04/xmlenc#sha256"
+            | "http://www.w3.org/2000/09/xmldsig#sha256" => Some(DigestAlgorithm::Sha256),
+            _ => None,
+        }
+    }
+
+    /// Looks up the digest used by a `<SignatureMethod>` URI.
+    pub fn from_signature_uri(uri: &str) -> Option<Self> {
+        match uri {
+            "http://www.w3.org/2000/09/xmldsig#rsa-sha1" => Some(DigestAlgorithm::Sha1),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => Some(DigestAlgorithm::Sha256),
+            _ => None,
+        }
+    }
+
+    /// The URI used in `<DigestMethod>` elements.
+    pub fn uri(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha1 => "http://www.w3.org/2000/09/xmldsig#sha1",
+            DigestAlgorithm::Sha256 => "http://www.w3.org/2001/04/xmlenc#sha256",
+        }
+    }
+
+    /// The URI used in the `<hash>` of files and dependencies. ClickOnce
+    /// names SHA-256 with a URI in the XML signature namespace here, rather
+    /// than the standard one used in signatures.
+    pub fn manifest_uri(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha1 => "http://www.w3.org/2000/09/xmldsig#sha1",
+            DigestAlgorithm::Sha256 => "http://www.w3.org/2000/09/xmldsig#sha256",
+        }
+    }
+
+    /// The URI used in `<SignatureMethod>` elements for RSA signatures.
+    pub fn signature_uri(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha1 => "http://www.w3.org/2000/09/xmldsig#rsa-sha1",
+            DigestAlgorithm::Sha256 => "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256",
+        }
+    }
+
+    /// Computes the digest of `data`.
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Sha1 => Sha1::digest(data).to_vec(),
+            DigestAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    /// Computes the digest of everything read from `reader`, returning the
+    /// number of bytes read and the digest.
+    pub fn digest_reader<R: Read>(self, reader: R) -> std::io::Result<(u64, Vec<u8>)> {
+        match self {
+            DigestAlgorithm::Sha1 => digest_reader::<Sha1, R>(reader),
+            DigestAlgorithm::Sha256 => digest_reader::<Sha256, R>(reader),
+        }
+    }
+}
+
+fn digest_reader<D: Digest, R: Read>(mut reader: R) -> std::io::Result<(u64, Vec<u8>)> {
+    let mut hasher = D::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok((size, hasher.finalize().to_vec()));
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate::to_hex;
+
+    #[test]
+    fn computes_known_digests() {
+        // FIPS 180-2 test vectors
+        assert_eq!(
+            to_hex(&DigestAlgorithm::Sha1.digest(b"abc"), false),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            to_hex(&DigestAlgorithm::Sha256.digest(b"abc"), false),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        // Longer than the read buffer, so it is hashed in several chunks
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        for algorithm in &[DigestAlgorithm::Sha1, DigestAlgorithm::Sha256] {
+            let (size, digest) = algorithm.digest_reader(data.as_slice()).unwrap();
+            assert_eq!(size, data.len() as u64);
+            assert_eq!(digest, algorithm.digest(&data));
+        }
+    }
+
+    #[test]
+    fn looks_up_uris() {
+        for algorithm in &[DigestAlgorithm::Sha1, DigestAlgorithm::Sha256] {
+            assert_eq!(DigestAlgorithm::from_uri(algorithm.uri()), Some(*algorithm));
+            assert_eq!(
+                DigestAlgorithm::from_uri(algorithm.manifest_uri()),
+                Some(*algorithm)
+            );
+            assert_eq!(
+                DigestAlgorithm::from_signature_uri(algorithm.signature_uri()),
+                Some(*algorithm)
+            );
+        }
+        assert_eq!(
+            DigestAlgorithm::from_uri("http://www.w3.org/2001/04/xmldsig-more#md5"),
+            None
+        );
+        assert_eq!(
+            DigestAlgorithm::from_signature_uri(DigestAlgorithm::Sha256.uri()),
+            None
+        );
+    }
+}
@@ -0,0 +1,638 @@
+//! A minimal element tree for editing and canonicalizing manifests
+//!
+//! The serde models only keep what they model, so they cannot be used to
+//! change a manifest and write it back out. [`Element`] keeps every
+//! element, attribute, comment and text node of a document, and produces the
+//! exclusive canonical form (https://www.w3.org/TR/xml-exc-c14n/) that
+//! manifest signatures are computed over.
+use crate::limits::syntax_error;
+use crate::{Error, ParseLimits};
+use std::collections::BTreeMap;
+use xml::attribute::OwnedAttribute;
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
+use xml::reader::XmlEvent;
+
+/// A node in an element tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// A child element
+    Element(Element),
+    /// Character data, including whitespace
+    Text(String),
+    /// A comment
+    Comment(String),
+    /// A processing instruction
+    ProcessingInstruction {
+        /// The target of the instruction
+        name: String,
+        /// The data following the target, if any
+        data: Option<String>,
+    },
+}
+
+/// An element and everything inside it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    /// The element's name, with its prefix and resolved namespace
+    pub name: OwnedName,
+    /// Namespaces declared on this element, as (prefix, URI) pairs. The
+    /// default namespace has an empty prefix.
+    pub namespaces: Vec<(String, String)>,
+    /// The element's attributes, in document order
+    pub attributes: Vec<OwnedAttribute>,
+    /// The element's children, in document order
+    pub children: Vec<Node>,
+}
+
+/// Escapes character data, as both the canonical form and
+/// [`Element::to_xml`] require.
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Escapes an attribute value. Whitespace characters are escaped so that
+/// they survive attribute value normalization when the output is parsed
+/// again.
+fn escape_attribute(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn push_name(name: &OwnedName, out: &mut String) {
+    if let Some(prefix) = &name.prefix {
+        out.push_str(prefix);
+        out.push(':');
+    }
+    out.push_str(&name.local_name);
+}
+
+fn push_namespace(prefix: &str, uri: &str, out: &mut String) {
+    out.push_str(" xmlns");
+    if !prefix.is_empty() {
+        out.push(':');
+        out.push_str(prefix);
+    }
+    out.push_str("=\"");
+    escape_attribute(uri, out);
+    out.push('"');
+}
+
+fn push_attribute(attribute: &OwnedAttribute, out: &mut String) {
+    out.push(' ');
+    push_name(&attribute.name, out);
+    out.push_str("=\"");
+    escape_attribute(&attribute.value, out);
+    out.push('"');
+}
+
+impl Element {
+    /// Creates an empty element.
+    pub fn new(name: OwnedName) -> Self {
+        Self {
+            name,
+            namespaces: Vec::new(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Parses a document, returning its root element. The default
+    /// [`ParseLimits`] are applied.
+    pub fn parse(contents: &[u8]) -> Result<Self, Error> {
+        Self::parse_with_limits(contents, &ParseLimits::default())
+    }
+
+    /// Parses a document, returning its root element, failing if the
+    /// document breaks any of `limits`.
+    ///
+    /// Comments and processing instructions outside the root element are
+    /// discarded.
+    pub fn parse_with_limits(contents: &[u8], limits: &ParseLimits) -> Result<Self, Error> {
+        limits.check_size(contents.len())?;
+        let contents = crate::encoding::decode(contents)?;
+        let mut reader = limits
+            .parser_config()
+            .ignore_comments(false)
+            .whitespace_to_characters(true)
+            .cdata_to_characters(true)
+            .create_reader(contents.as_bytes());
+
+        // Open elements, with the namespaces in scope for each.
+        let mut stack: Vec<(Element, Namespace)> = Vec::new();
+        loop {
+            let event = reader.next().map_err(|e| {
+                limits
+                    .doctype_error(&reader)
+                    .unwrap_or_else(|| syntax_error(e))
+            })?;
+            let depth = match event {
+                XmlEvent::StartElement { .. } => stack.len() + 1,
+                _ => stack.len(),
+            };
+            limits.check_event(&reader, &event, depth)?;
+
+            let node = match event {
+                XmlEvent::StartElement {
+                    name,
+                    attributes,
+                    namespace,
+                } => {
+                    let parent = stack.last().map(|(_, scope)| scope);
+                    let namespaces = namespace
+                        .0
+                        .iter()
+                        .filter(|(prefix, _)| !matches!(prefix.as_str(), "xml" | "xmlns"))
+                        .filter(|(prefix, uri)| {
+                            parent.and_then(|p| p.get(prefix.as_str())).unwrap_or("") != *uri
+                        })
+                        .map(|(prefix, uri)| (prefix.clone(), uri.clone()))
+                        .collect();
+                    stack.push((
+                        Element {
+                            name,
+                            namespaces,
+                            attributes,
+                            children: Vec::new(),
+                        },
+                        namespace,
+                    ));
+                    continue;
+                }
+                XmlEvent::EndElement { .. } => match stack.pop() {
+                    Some((element, _)) if stack.is_empty() => return Ok(element),
+                    Some((element, _)) => Node::Element(element),
+                    None => continue,
+                },
+                XmlEvent::Characters(text) => Node::Text(text),
+                XmlEvent::Comment(text) => Node::Comment(text),
+                XmlEvent::ProcessingInstruction { name, data } => {
+                    Node::ProcessingInstruction { name, data }
+                }
+                XmlEvent::EndDocument => {
+                    return Err(Error::XML(serde_xml_rs::Error::Custom {
+                        field: "document has no root element".to_string(),
+                    }))
+                }
+                _ => continue,
+            };
+            if let Some((parent, _)) = stack.last_mut() {
+                parent.push_node(node);
+            }
+        }
+    }
+
+    /// Appends a node, merging adjacent text.
+    fn push_node(&mut self, node: Node) {
+        if let (Node::Text(text), Some(Node::Text(previous))) = (&node, self.children.last_mut()) {
+            previous.push_str(text);
+        } else {
+            self.children.push(node);
+        }
+    }
+
+    /// Whether the element has the given namespace and local name.
+    pub fn is(&self, namespace: &str, local_name: &str) -> bool {
+        self.name.local_name == local_name
+            && self.name.namespace.as_deref().unwrap_or("") == namespace
+    }
+
+    /// The value of an unqualified attribute.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|a| a.name.namespace.is_none() && a.name.local_name == name)
+            .map(|a| a.value.as_str())
+    }
+
+    /// Sets the value of an unqualified attribute, adding it after the
+    /// existing attributes if it is not present.
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match self
+            .attributes
+            .iter_mut()
+            .find(|a| a.name.namespace.is_none() && a.name.local_name == name)
+        {
+            Some(attribute) => attribute.value = value.to_string(),
+            None => self
+                .attributes
+                .push(OwnedAttribute::new(OwnedName::local(name), value)),
+        }
+    }
+
+    /// Iterates over the child elements.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// The first child element with the given namespace and local name.
+    pub fn child(&self, namespace: &str, local_name: &str) -> Option<&Element> {
+        self.elements().find(|e| e.is(namespace, local_name))
+    }
+
+    /// The first child element with the given namespace and local name.
+    pub fn child_mut(&mut self, namespace: &str, local_name: &str) -> Option<&mut Element> {
+        self.children.iter_mut().find_map(|node| match node {
+            Node::Element(element) if element.is(namespace, local_name) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Creates an empty element in `namespace`, to be added somewhere inside
+    /// this one. The prefix this element declares for the namespace is
+    /// reused; otherwise the new element declares it as its default.
+    pub fn create_element(&self, namespace: &str, local_name: &str) -> Element {
+        let prefix = self
+            .namespaces
+            .iter()
+            .find(|(_, uri)| uri == namespace)
+            .map(|(prefix, _)| prefix.clone());
+        let mut element = Element::new(OwnedName {
+            local_name: local_name.to_string(),
+            namespace: Some(namespace.to_string()),
+            prefix: prefix.clone().filter(|p| !p.is_empty()),
+        });
+        if prefix.is_none() {
+            element
+                .namespaces
+                .push((String::new(), namespace.to_string()));
+        }
+        element
+    }
+
+    /// Removes every child element with the given namespace and local name.
+    pub fn remove_children(&mut self, namespace: &str, local_name: &str) {
+        self.children.retain(|node| match node {
+            Node::Element(element) => !element.is(namespace, local_name),
+            _ => true,
+        });
+    }
+
+    /// The text directly inside the element.
+    pub fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Replaces the element's children with `text`.
+    pub fn set_text(&mut self, text: &str) {
+        self.children = vec![Node::Text(text.to_string())];
+    }
+
+    /// Serializes the element and its children.
+    pub fn to_xml(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+
+    /// Serializes the element as a UTF-8 document with an XML declaration.
+    pub fn to_document(&self) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        self.write(&mut out);
+        out
+    }
+
+    /// Serializes the element as an indented UTF-8 document with an XML
+    /// declaration, ending in a newline.
+    ///
+    /// Each child element starts a new line, indented by `indent` once per
+    /// level, and whitespace-only text between elements is dropped. Elements
+    /// that mix text with other nodes are written unchanged, as indenting
+    /// them would alter their text.
+    pub fn to_pretty_document(&self, indent: &str) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        self.write_pretty(indent, 0, &mut out);
+        out.push('\n');
+        out
+    }
+
+    fn write_pretty(&self, indent: &str, depth: usize, out: &mut String) {
+        let mixed = self.children.iter().any(|node| match node {
+            Node::Text(text) => !text.trim().is_empty(),
+            _ => false,
+        }) && self.children.len() > 1;
+        if mixed
+            || self
+                .children
+                .iter()
+                .all(|node| matches!(node, Node::Text(_)))
+        {
+            self.write(out);
+            return;
+        }
+
+        self.write_start(out);
+        out.push('>');
+        for node in &self.children {
+            if let Node::Text(_) = node {
+                continue;
+            }
+            out.push('\n');
+            out.push_str(&indent.repeat(depth + 1));
+            match node {
+                Node::Element(element) => element.write_pretty(indent, depth + 1, out),
+                Node::Comment(text) => {
+                    out.push_str("<!--");
+                    out.push_str(text);
+                    out.push_str("-->");
+                }
+                Node::ProcessingInstruction { name, data } => write_pi(name, data, out),
+                Node::Text(_) => {}
+            }
+        }
+        out.push('\n');
+        out.push_str(&indent.repeat(depth));
+        out.push_str("</");
+        push_name(&self.name, out);
+        out.push('>');
+    }
+
+    fn write_start(&self, out: &mut String) {
+        out.push('<');
+        push_name(&self.name, out);
+        for (prefix, uri) in &self.namespaces {
+            push_namespace(prefix, uri, out);
+        }
+        for attribute in &self.attributes {
+            push_attribute(attribute, out);
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        self.write_start(out);
+        if self.children.is_empty() {
+            out.push_str(" />");
+            return;
+        }
+        out.push('>');
+        for node in &self.children {
+            match node {
+                Node::Element(element) => element.write(out),
+                Node::Text(text) => escape_text(text, out),
+                Node::Comment(text) => {
+                    out.push_str("<!--");
+                    out.push_str(text);
+                    out.push_str("-->");
+                }
+                Node::ProcessingInstruction { name, data } => write_pi(name, data, out),
+            }
+        }
+        out.push_str("</");
+        push_name(&self.name, out);
+        out.push('>');
+    }
+
+    /// The exclusive canonical form of the element, without comments.
+    pub fn canonicalize(&self) -> String {
+        let mut out = String::new();
+        self.write_canonical(&BTreeMap::new(), None, &mut out);
+        out
+    }
+
+    /// The exclusive canonical form of the element, without comments, and
+    /// leaving out `excluded`. This is the enveloped signature transform
+    /// when `excluded` is the signature itself.
+    pub fn canonicalize_excluding(&self, excluded: &Element) -> String {
+        let mut out = String::new();
+        self.write_canonical(&BTreeMap::new(), Some(excluded), &mut out);
+        out
+    }
+
+    fn write_canonical(
+        &self,
+        rendered: &BTreeMap<String, String>,
+        excluded: Option<&Element>,
+        out: &mut String,
+    ) {
+        // Only namespaces visibly used by the element or its attributes
+        // are output, and only if an output ancestor has not already
+        // declared them with the same value.
+        let mut used = BTreeMap::new();
+        let _ = used.insert(
+            self.name.prefix.clone().unwrap_or_default(),
+            self.name.namespace.clone().unwrap_or_default(),
+        );
+        for attribute in &self.attributes {
+            if let (Some(prefix), Some(namespace)) =
+                (&attribute.name.prefix, &attribute.name.namespace)
+            {
+                if prefix != "xml" {
+                    let _ = used.insert(prefix.clone(), namespace.clone());
+                }
+            }
+        }
+
+        let mut rendered = rendered.clone();
+        out.push('<');
+        push_name(&self.name, out);
+        for (prefix, uri) in used {
+            if rendered.get(&prefix).map(String::as_str).unwrap_or("") != uri {
+                push_namespace(&prefix, &uri, out);
+                let _ = rendered.insert(prefix, uri);
+            }
+        }
+
+        let mut attributes: Vec<&OwnedAttribute> = self.attributes.iter().collect();
+        attributes.sort_by(|a, b| {
+            (
+                a.name.namespace.as_deref().unwrap_or(""),
+                &a.name.local_name,
+            )
+                .cmp(&(
+                    b.name.namespace.as_deref().unwrap_or(""),
+                    &b.name.local_name,
+                ))
+        });
+        for attribute in attributes {
+            push_attribute(attribute, out);
+        }
+        out.push('>');
+
+        for node in &self.children {
+            match node {
+                Node::Element(element) => {
+                    if !excluded.is_some_and(|e| std::ptr::eq(e, element)) {
+                        element.write_canonical(&rendered, excluded, out);
+                    }
+                }
+                Node::Text(text) => escape_text(text, out),
+                Node::Comment(_) => {}
+                Node::ProcessingInstruction { name, data } => write_pi(name, data, out),
+            }
+        }
+
+        out.push_str("</");
+        push_name(&self.name, out);
+        out.push('>');
+    }
+}
+
+fn write_pi(name: &str, data: &Option<String>, out: &mut String) {
+    out.push_str("<?");
+    out.push_str(name);
+    if let Some(data) = data {
+        out.push(' ');
+        out.push_str(data);
+    }
+    out.push_str("?>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_documents() {
+        let contents = include_bytes!("example/sample.application");
+        let root = Element::parse(contents).unwrap();
+        assert!(root.is("urn:schemas-microsoft-com:asm.v1", "assembly"));
+        assert_eq!(root.name.prefix.as_deref(), Some("asmv1"));
+
+        let reparsed = Element::parse(root.to_document().as_bytes()).unwrap();
+        assert_eq!(root, reparsed);
+        assert_eq!(root.canonicalize(), reparsed.canonicalize());
+    }
+
+    #[test]
+    fn pretty_prints_documents() {
+        let root = Element::parse(
+            b"<a x=\"1\">\r\n<b>  text  </b><!-- note --><c><d/></c><e>mixed <f/></e></a>",
+        )
+        .unwrap();
+        assert_eq!(
+            root.to_pretty_document("  "),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <a x=\"1\">\n  <b>  text  </b>\n  <!-- note -->\n  <c>\n    <d />\n  </c>\n  \
+             <e>mixed <f /></e>\n</a>\n"
+        );
+    }
+
+    #[test]
+    fn canonicalizes_exclusively() {
+        let root = Element::parse(
+            br#"<?xml version="1.0"?>
+<!-- dropped -->
+<a:root xmlns:a="urn:a" xmlns:b="urn:b" xmlns:unused="urn:unused"><!-- comment -->
+  <b:child z="1" b:y="2" a="&quot;3&#xA;"/>
+  <a:other>x &amp; y &lt; z</a:other>
+</a:root>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            root.canonicalize(),
+            "<a:root xmlns:a=\"urn:a\">\n  \
+             <b:child xmlns:b=\"urn:b\" a=\"&quot;3&#xA;\" z=\"1\" b:y=\"2\"></b:child>\n  \
+             <a:other>x &amp; y &lt; z</a:other>\n</a:root>"
+        );
+
+        let other = root.child("urn:a", "other").unwrap();
+        assert_eq!(
+            other.canonicalize(),
+            "<a:other xmlns:a=\"urn:a\">x &amp; y &lt; z</a:other>"
+        );
+        assert!(!root.canonicalize_excluding(other).contains("a:other"));
+    }
+
+    /// The example from section 2.2 of the Exclusive XML Canonicalization
+    /// recommendation: the same subtree in two documents has the same
+    /// exclusive canonical form, whatever namespaces and `xml:` attributes
+    /// its ancestors declare.
+    #[test]
+    fn canonicalizes_w3c_exclusive_example() {
+        let first = Element::parse(
+            br#"<n0:local xmlns:n0="foo:bar" xmlns:n3="ftp://example.org">
+  <n1:elem2 xmlns:n1="http://example.net" xml:lang="en">
+    <n3:stuff xmlns:n3="ftp://example.org"/>
+  </n1:elem2>
+</n0:local>"#,
+        )
+        .unwrap();
+        let second = Element::parse(
+            br#"<n2:pdu xmlns:n1="http://example.com"
+           xmlns:n2="http://foo.example"
+           xml:lang="fr"
+           xml:foo="bar">
+  <n1:elem2 xmlns:n1="http://example.net" xml:lang="en">
+    <n3:stuff xmlns:n3="ftp://example.org"/>
+  </n1:elem2>
+</n2:pdu>"#,
+        )
+        .unwrap();
+
+        let expected = "<n1:elem2 xmlns:n1=\"http://example.net\" xml:lang=\"en\">\n    \
+                        <n3:stuff xmlns:n3=\"ftp://example.org\"></n3:stuff>\n  </n1:elem2>";
+        let first = first.child("http://example.net", "elem2").unwrap();
+        let second = second.child("http://example.net", "elem2").unwrap();
+        assert_eq!(first.canonicalize(), expected);
+        assert_eq!(second.canonicalize(), expected);
+    }
+
+    /// The character example from section 3.4 of the Canonical XML
+    /// recommendation, without its DTD, which exclusive canonicalization
+    /// treats in the same way.
+    #[test]
+    fn canonicalizes_w3c_character_example() {
+        let root = Element::parse(
+            br#"<doc>
+   <text>First line&#x0d;&#10;Second line</text>
+   <value>&#x32;</value>
+   <compute><![CDATA[value>"0" && value<"10" ?"valid":"error"]]></compute>
+   <compute expr='value>"0" &amp;&amp; value&lt;"10" ?"valid":"error"'>valid</compute>
+   <norm attr=' &apos;   &#x20;&#13;&#xa;&#9;   &apos; '/>
+</doc>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            root.canonicalize(),
+            "<doc>\n   \
+             <text>First line&#xD;\nSecond line</text>\n   \
+             <value>2</value>\n   \
+             <compute>value&gt;\"0\" &amp;&amp; value&lt;\"10\" ?\"valid\":\"error\"</compute>\n   \
+             <compute expr=\"value>&quot;0&quot; &amp;&amp; value&lt;&quot;10&quot; \
+             ?&quot;valid&quot;:&quot;error&quot;\">valid</compute>\n   \
+             <norm attr=\" '    &#xD;&#xA;&#x9;   ' \"></norm>\n</doc>"
+        );
+    }
+
+    #[test]
+    fn canonicalizes_default_namespaces() {
+        let root = Element::parse(
+            br#"<root xmlns="urn:a"><child xmlns=""><x xmlns="urn:a"/></child></root>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            root.canonicalize(),
+            "<root xmlns=\"urn:a\"><child xmlns=\"\"><x xmlns=\"urn:a\"></x></child></root>"
+        );
+        assert_eq!(
+            root.to_xml(),
+            "<root xmlns=\"urn:a\"><child xmlns=\"\"><x xmlns=\"urn:a\" /></child></root>"
+        );
+    }
+}
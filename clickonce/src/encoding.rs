@@ -0,0 +1,185 @@
+//! Detection and decoding of the character encoding of manifest files
+//!
+//! ClickOnce manifests are usually UTF-8, but manifests saved by Windows
+//! tools are frequently UTF-16. The encoding is detected from the byte
+//! order mark, falling back to the first bytes of the document and the
+//! `encoding` attribute of the XML declaration, as described in
+//! https://www.w3.org/TR/xml/#sec-guessing
+use crate::Error;
+
+/// Character encodings understood by the manifest parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8 (including plain ASCII)
+    Utf8,
+    /// UTF-16, little endian
+    Utf16Le,
+    /// UTF-16, big endian
+    Utf16Be,
+    /// ISO-8859-1 (Latin-1)
+    Latin1,
+}
+
+/// Detects the encoding of an XML document.
+///
+/// Returns the encoding along with the length of any byte order mark that
+/// should be skipped before decoding.
+pub fn detect(contents: &[u8]) -> Result<(Encoding, usize), Error> {
+    match contents {
+        [0xef, 0xbb, 0xbf, ..] => Ok((Encoding::Utf8, 3)),
+        [0xff, 0xfe, ..] => Ok((Encoding::Utf16Le, 2)),
+        [0xfe, 0xff, ..] => Ok((Encoding::Utf16Be, 2)),
+        [0x3c, 0x00, 0x3f, 0x00, ..] => Ok((Encoding::Utf16Le, 0)),
+        [0x00, 0x3c, 0x00, 0x3f, ..] => Ok((Encoding::Utf16Be, 0)),
+        _ => match declared_encoding(contents) {
+            Some(label) => Ok((encoding_for_label(&label)?, 0)),
+            None => Ok((Encoding::Utf8, 0)),
+        },
+    }
+}
+
+/// Decodes an XML document to a string, whatever its encoding.
+///
+/// The byte order mark is removed, and the `encoding` attribute of the XML
+/// declaration is rewritten to `utf-8` so that the result can be handed
+/// straight to the XML parser.
+pub fn decode(contents: &[u8]) -> Result<String, Error> {
+    let (encoding, bom_len) = detect(contents)?;
+    let contents = &contents[bom_len..];
+
+    let decoded = match encoding {
+        Encoding::Utf8 => String::from_utf8(contents.to_vec())
+            .map_err(|e| Error::Encoding(format!("invalid UTF-8: {}", e)))?,
+        Encoding::Utf16Le => decode_utf16(contents, u16::from_le_bytes)?,
+        Encoding::Utf16Be => decode_utf16(contents, u16::from_be_bytes)?,
+        Encoding::Latin1 => contents.iter().map(|&b| char::from(b)).collect(),
+    };
+
+    Ok(rewrite_declared_encoding(decoded))
+}
+
+fn decode_utf16(contents: &[u8], convert: fn([u8; 2]) -> u16) -> Result<String, Error> {
+    if !contents.len().is_multiple_of(2) {
+        return Err(Error::Encoding(
+            "UTF-16 document has an odd number of bytes".to_string(),
+        ));
+    }
+
+    let units: Vec<u16> = contents
+        .chunks_exact(2)
+        .map(|pair| convert([pair[0], pair[1]]))
+        .collect();
+
+    String::from_utf16(&units).map_err(|e| Error::Encoding(format!("invalid UTF-16: {}", e)))
+}
+
+fn encoding_for_label(label: &str) -> Result<Encoding, Error> {
+    match label.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" | "us-ascii" | "ascii" => Ok(Encoding::Utf8),
+        "iso-8859-1" | "latin1" | "latin-1" => Ok(Encoding::Latin1),
+        // A UTF-16 declaration in an ASCII-compatible document without a
+        // byte order mark cannot be trusted; the bytes themselves tell us
+        // the document is not UTF-16.
+        "utf-16" | "utf-16le" | "utf-16be" => Err(Error::Encoding(format!(
+            "document declares {} but is not UTF-16 encoded",
+            label
+        ))),
+        _ => Err(Error::Encoding(format!("unsupported encoding {}", label))),
+    }
+}
+
+/// Returns the byte range of the `encoding` attribute value in the XML
+/// declaration, if there is one.
+fn encoding_value_range(contents: &[u8]) -> Option<(usize, usize)> {
+    if !contents.starts_with(b"<?xml") {
+        return None;
+    }
+    let end = contents.windows(2).position(|w| w == b"?>")?;
+    let declaration = &contents[..end];
+
+    let attr = declaration.windows(8).position(|w| w == b"encoding")?;
+    let mut pos = attr + 8;
+    while pos < declaration.len() && declaration[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    if declaration.get(pos) != Some(&b'=') {
+        return None;
+    }
+    pos += 1;
+    while pos < declaration.len() && declaration[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    let quote = *declaration.get(pos)?;
+    if quote != b'"' && quote != b'\'' {
+        return None;
+    }
+    let start = pos + 1;
+    let len = declaration[start..].iter().position(|&b| b == quote)?;
+    Some((start, start + len))
+}
+
+fn declared_encoding(contents: &[u8]) -> Option<String> {
+    let (start, end) = encoding_value_range(contents)?;
+    std::str::from_utf8(&contents[start..end])
+        .ok()
+        .map(|s| s.to_string())
+}
+
+fn rewrite_declared_encoding(mut decoded: String) -> String {
+    if let Some((start, end)) = encoding_value_range(decoded.as_bytes()) {
+        decoded.replace_range(start..end, "utf-8");
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = "<?xml version=\"1.0\" encoding=\"utf-16\"?><a>caf\u{e9}</a>";
+
+    fn utf16(contents: &str, convert: fn(u16) -> [u8; 2], bom: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if bom {
+            bytes.extend_from_slice(&convert(0xfeff));
+        }
+        for unit in contents.encode_utf16() {
+            bytes.extend_from_slice(&convert(unit));
+        }
+        bytes
+    }
+
+    #[test]
+    fn detects_byte_order_marks() {
+        assert_eq!(detect(b"\xef\xbb\xbf<a/>").unwrap(), (Encoding::Utf8, 3));
+        assert_eq!(detect(b"\xff\xfe<\0").unwrap(), (Encoding::Utf16Le, 2));
+        assert_eq!(detect(b"\xfe\xff\0<").unwrap(), (Encoding::Utf16Be, 2));
+        assert_eq!(detect(b"<a/>").unwrap(), (Encoding::Utf8, 0));
+    }
+
+    #[test]
+    fn decodes_utf16_with_and_without_bom() {
+        let expected = DOCUMENT.replace("utf-16", "utf-8");
+        for &bom in &[true, false] {
+            let le = utf16(DOCUMENT, u16::to_le_bytes, bom);
+            let be = utf16(DOCUMENT, u16::to_be_bytes, bom);
+            assert_eq!(decode(&le).unwrap(), expected);
+            assert_eq!(decode(&be).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn decodes_declared_latin1() {
+        let contents = b"<?xml version='1.0' encoding='ISO-8859-1'?><a>caf\xe9</a>";
+        assert_eq!(
+            decode(contents).unwrap(),
+            "<?xml version='1.0' encoding='utf-8'?><a>caf\u{e9}</a>"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_encodings() {
+        let contents = b"<?xml version=\"1.0\" encoding=\"EBCDIC\"?><a/>";
+        assert!(matches!(decode(contents), Err(Error::Encoding(_))));
+    }
+}
@@ -0,0 +1,488 @@
+//! Offline reading of applications installed in the ClickOnce store
+//!
+//! ClickOnce keeps a copy of each installed deployment and application
+//! manifest in a `manifests` folder beside the application's files:
+//!
+//! ```text
+//! Apps\2.0\<random>\<random>\manifests\<store name>.manifest
+//! Apps\2.0\<random>\<random>\<store name>\...
+//! ```
+//!
+//! [`Installation::read`] works on a copy of the `Apps\2.0` directory, such
+//! as one taken from a user's profile, without any network access.
+use crate::applicationmanifest::{self, ApplicationManifest};
+use crate::deploymentmanifest::{DeploymentManifest, Hash};
+use crate::store::{store_folders, StoreName};
+use crate::version::Version;
+use crate::{encoding, pathsafety, Error, ParseLimits};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The deployments found in a copy of the ClickOnce store
+#[derive(Debug)]
+pub struct Installation {
+    /// Installed deployments, ordered by name and version
+    pub deployments: Vec<InstalledDeployment>,
+    /// Manifests that could not be read, which are skipped
+    pub errors: Vec<ManifestError>,
+}
+
+/// A manifest in the store that could not be read
+#[derive(Debug)]
+pub struct ManifestError {
+    /// Path of the manifest
+    pub path: PathBuf,
+    /// Why it could not be read
+    pub error: Error,
+}
+
+/// A deployment found in the store
+#[derive(Debug)]
+pub struct InstalledDeployment {
+    /// Path of the store's copy of the deployment manifest
+    pub manifest_path: PathBuf,
+    /// The deployment manifest
+    pub manifest: DeploymentManifest,
+    /// The application the deployment installed, if its manifest was found
+    pub application: Option<InstalledApplication>,
+}
+
+/// An application found in the store
+#[derive(Debug)]
+pub struct InstalledApplication {
+    /// Path of the store's copy of the application manifest
+    pub manifest_path: PathBuf,
+    /// The application manifest
+    pub manifest: ApplicationManifest,
+    /// Folder holding the application's files, if one was found
+    pub folder: Option<PathBuf>,
+    /// The files and assemblies listed in the application manifest
+    pub files: Vec<InstalledFile>,
+}
+
+/// A file listed in an installed application manifest
+#[derive(Debug)]
+pub struct InstalledFile {
+    /// Name of the file, as it appears in the manifest
+    pub name: String,
+    /// What was found when the file was checked against the manifest
+    pub status: FileStatus,
+}
+
+/// The result of checking an installed file against its manifest entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+    /// The file's size and hash match the manifest
+    Verified,
+    /// The file exists, but the manifest records no hash to check it with
+    Unverified,
+    /// The file is optional and has not been downloaded
+    NotDownloaded,
+    /// The file does not exist
+    Missing,
+    /// The file's size differs from the manifest
+    SizeMismatch {
+        /// Size recorded in the manifest
+        expected: u64,
+        /// Size of the file on disk
+        actual: u64,
+    },
+    /// The file's hash differs from the manifest
+    HashMismatch,
+    /// The manifest names the file with a path that is not safe to follow
+    UnsafePath,
+}
+
+impl FileStatus {
+    /// Whether the status indicates a damaged installation.
+    pub fn is_problem(&self) -> bool {
+        !matches!(
+            self,
+            FileStatus::Verified | FileStatus::Unverified | FileStatus::NotDownloaded
+        )
+    }
+}
+
+impl Installation {
+    /// Reads the deployments installed in a copy of the `Apps\2.0` directory.
+    pub fn read(apps: &Path) -> Result<Self, Error> {
+        Self::read_with_limits(apps, &ParseLimits::default())
+    }
+
+    /// Reads the deployments installed in a copy of the `Apps\2.0`
+    /// directory, skipping manifests that break the given parsing limits or
+    /// cannot be read. Those are listed in [`Installation::errors`].
+    pub fn read_with_limits(apps: &Path, limits: &ParseLimits) -> Result<Self, Error> {
+        let folders = store_folders(apps)?;
+        let mut deployments = Vec::new();
+        let mut errors = Vec::new();
+        for manifests in manifest_folders(apps)? {
+            let mut deployment_manifests = Vec::new();
+            let mut application_manifests = Vec::new();
+            for path in manifest_files(&manifests)? {
+                match read_manifest(&path, limits) {
+                    Ok(Manifest::Deployment(manifest)) => {
+                        deployment_manifests.push((path, manifest))
+                    }
+                    Ok(Manifest::Application(manifest)) => {
+                        application_manifests.push((path, manifest))
+                    }
+                    Err(error) => errors.push(ManifestError { path, error }),
+                }
+            }
+
+            let store = manifests.parent().unwrap_or(&manifests);
+            for (manifest_path, manifest) in deployment_manifests {
+                let application = match application_manifests
+                    .iter()
+                    .position(|(_, application)| installs(&manifest, application))
+                {
+                    Some(index) => {
+                        let (path, application) = application_manifests.swap_remove(index);
+                        match InstalledApplication::read(path.clone(), application, store, &folders)
+                        {
+                            Ok(application) => Some(application),
+                            Err(error) => {
+                                errors.push(ManifestError { path, error });
+                                None
+                            }
+                        }
+                    }
+                    None => None,
+                };
+                deployments.push(InstalledDeployment {
+                    manifest_path,
+                    manifest,
+                    application,
+                });
+            }
+        }
+        deployments.sort_by(|a, b| {
+            let a_identity = &a.manifest.assembly.assembly_identity;
+            let b_identity = &b.manifest.assembly.assembly_identity;
+            a_identity
+                .name
+                .cmp(&b_identity.name)
+                .then_with(|| a.version().ok().cmp(&b.version().ok()))
+        });
+        Ok(Self {
+            deployments,
+            errors,
+        })
+    }
+}
+
+enum Manifest {
+    Deployment(DeploymentManifest),
+    Application(ApplicationManifest),
+}
+
+/// Reads a manifest from the store, reading and parsing it only within
+/// `limits`. Both kinds of manifest have an `<assembly>` root, but only
+/// deployment manifests have a `<deployment>` element in it, so the pass
+/// that checks the limits also picks which model to deserialize.
+fn read_manifest(path: &Path, limits: &ParseLimits) -> Result<Manifest, Error> {
+    let contents = limits.read_to_end(fs::File::open(path)?)?;
+    let contents = encoding::decode(&contents)?;
+    let contents = contents.trim_start_matches('\u{feff}');
+    let mut deployment = false;
+    limits.check_elements(contents, |depth, name| {
+        deployment |= depth == 2 && name.local_name == "deployment";
+    })?;
+    if deployment {
+        Ok(Manifest::Deployment(serde_xml_rs::from_str(contents)?))
+    } else {
+        Ok(Manifest::Application(serde_xml_rs::from_str(contents)?))
+    }
+}
+
+impl InstalledDeployment {
+    /// The installed version of the deployment.
+    pub fn version(&self) -> Result<Version, Error> {
+        self.manifest.assembly.assembly_identity.version.parse()
+    }
+
+    /// The files that are missing or do not match the application manifest.
+    pub fn problems(&self) -> impl Iterator<Item = &InstalledFile> {
+        self.application
+            .iter()
+            .flat_map(|application| application.files.iter())
+            .filter(|file| file.status.is_problem())
+    }
+}
+
+impl InstalledApplication {
+    fn read(
+        manifest_path: PathBuf,
+        manifest: ApplicationManifest,
+        store: &Path,
+        folders: &[PathBuf],
+    ) -> Result<Self, Error> {
+        // Folder names only hold the major and minor version, so several
+        // versions of an application can match its store name. The store's
+        // copy of the manifest is named after the folder of the exact
+        // version it installed.
+        let name = StoreName::from_application(&manifest.assembly.assembly_identity)?;
+        let stem = manifest_path.file_stem().and_then(|stem| stem.to_str());
+        let folder = folders
+            .iter()
+            .filter(|folder| folder.parent() == Some(store))
+            .find(|folder| {
+                folder
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|folder_name| {
+                        name.matches(folder_name)
+                            && stem.is_some_and(|stem| stem.eq_ignore_ascii_case(folder_name))
+                    })
+            })
+            .cloned();
+
+        let mut files = Vec::new();
+        for file in &manifest.assembly.file {
+            let status = match &folder {
+                Some(folder) => check_file(
+                    folder,
+                    &file.name,
+                    Some(file.size),
+                    file.hash.as_ref(),
+                    file.optional.unwrap_or(false),
+                )?,
+                None => FileStatus::Missing,
+            };
+            files.push(InstalledFile {
+                name: file.name.clone(),
+                status,
+            });
+        }
+        for assembly in installed_assemblies(&manifest) {
+            if let Some(codebase) = &assembly.codebase {
+                let status = match &folder {
+                    Some(folder) => check_file(
+                        folder,
+                        codebase,
+                        assembly.size,
+                        assembly.hash.as_ref(),
+                        false,
+                    )?,
+                    None => FileStatus::Missing,
+                };
+                files.push(InstalledFile {
+                    name: codebase.clone(),
+                    status,
+                });
+            }
+        }
+
+        Ok(Self {
+            manifest_path,
+            manifest,
+            folder,
+            files,
+        })
+    }
+}
+
+fn installed_assemblies(
+    manifest: &ApplicationManifest,
+) -> impl Iterator<Item = &applicationmanifest::DependentAssembly> {
+    manifest
+        .assembly
+        .dependency
+        .iter()
+        .filter_map(|dependency| dependency.dependent_assembly.as_ref())
+}
+
+/// Whether `application` is the application installed by `deployment`.
+fn installs(deployment: &DeploymentManifest, application: &ApplicationManifest) -> bool {
+    let application = &application.assembly.assembly_identity;
+    deployment.assembly.dependency.iter().any(|dependency| {
+        let identity = &dependency.dependent_assembly.assembly_identity;
+        identity.name.eq_ignore_ascii_case(&application.name)
+            && identity.version == application.version
+            && application
+                .public_key_token
+                .as_deref()
+                .is_none_or(|token| identity.public_key_token.eq_ignore_ascii_case(token))
+    })
+}
+
+/// Checks a file in `folder` against its size and hash in a manifest.
+fn check_file(
+    folder: &Path,
+    name: &str,
+    size: Option<u64>,
+    hash: Option<&Hash>,
+    optional: bool,
+) -> Result<FileStatus, Error> {
+    let path = match pathsafety::join(folder, name) {
+        Ok(path) => path,
+        Err(_) => return Ok(FileStatus::UnsafePath),
+    };
+    if !path.is_file() {
+        return Ok(if optional {
+            FileStatus::NotDownloaded
+        } else {
+            FileStatus::Missing
+        });
+    }
+
+    let algorithm = match hash.and_then(Hash::algorithm) {
+        Some(algorithm) => algorithm,
+        None => {
+            let actual = fs::metadata(&path)?.len();
+            return Ok(match size {
+                Some(expected) if expected != actual => {
+                    FileStatus::SizeMismatch { expected, actual }
+                }
+                _ => FileStatus::Unverified,
+            });
+        }
+    };
+    let (actual, digest) = algorithm.digest_reader(fs::File::open(&path)?)?;
+    Ok(match (size, hash) {
+        (Some(expected), _) if expected != actual => FileStatus::SizeMismatch { expected, actual },
+        (_, Some(hash)) if !hash.matches(&digest) => FileStatus::HashMismatch,
+        _ => FileStatus::Verified,
+    })
+}
+
+/// The `manifests` folders two levels of random folders below `apps`.
+fn manifest_folders(apps: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut folders = Vec::new();
+    for first in fs::read_dir(apps)? {
+        let first = first?.path();
+        if !first.is_dir() {
+            continue;
+        }
+        for second in fs::read_dir(&first)? {
+            let manifests = second?.path().join("manifests");
+            if manifests.is_dir() {
+                folders.push(manifests);
+            }
+        }
+    }
+    folders.sort();
+    Ok(folders)
+}
+
+fn manifest_files(folder: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        let is_manifest = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("manifest"));
+        if is_manifest && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::DigestAlgorithm;
+    use crate::dom::Element;
+    use crate::update::{update_application_manifest, update_deployment_manifest};
+
+    fn write(root: &Path, name: &str, contents: &[u8]) {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn reads_installed_deployment() {
+        let apps = tempfile::tempdir().unwrap();
+        let store = apps.path().join("ABCDEFGH.IJK").join("LMNOPQRS.TUV");
+        let folder = store.join("my a...exe_43cb1e8e7a352766_0001.0000_none_0123456789abcdef");
+        write(&folder, "MyApplication.exe", b"executable");
+        write(&folder, "MyApplication.exe.config", b"<configuration />");
+        write(&folder, "CardDataSource/client_DMU.xml", b"<cards />");
+        write(
+            &folder,
+            "CardDataSource/CARDNAME_STRING.xml",
+            b"<strings />",
+        );
+
+        let mut application =
+            Element::parse(include_bytes!("example/sample.exe.manifest")).unwrap();
+        let _ = update_application_manifest(&mut application, &folder, DigestAlgorithm::Sha256)
+            .unwrap();
+        let application = application.to_document();
+        let mut deployment = Element::parse(include_bytes!("example/sample.application")).unwrap();
+        update_deployment_manifest(
+            &mut deployment,
+            application.as_bytes(),
+            DigestAlgorithm::Sha256,
+        )
+        .unwrap();
+        let manifests = store.join("manifests");
+        write(
+            &manifests,
+            "my a...exe_43cb1e8e7a352766_0001.0000_none_0123456789abcdef.manifest",
+            application.as_bytes(),
+        );
+        write(
+            &manifests,
+            "my a...app_43cb1e8e7a352766_0001.0000_fedcba9876543210.manifest",
+            deployment.to_document().as_bytes(),
+        );
+
+        // Another version with the same major and minor version, whose
+        // folder sorts first
+        write(
+            &store.join("my a...exe_43cb1e8e7a352766_0001.0000_none_0000000000000000"),
+            "MyApplication.exe",
+            b"older",
+        );
+        write(&manifests, "broken.manifest", b"<assembly>");
+
+        // Corrupt one file and lose another after installation
+        write(&folder, "CardDataSource/client_DMU.xml", b"<cardz />");
+        fs::remove_file(folder.join("MyApplication.exe.config")).unwrap();
+
+        let installation = Installation::read(apps.path()).unwrap();
+        assert_eq!(installation.deployments.len(), 1);
+        assert_eq!(installation.errors.len(), 1);
+        assert_eq!(
+            installation.errors[0].path,
+            manifests.join("broken.manifest")
+        );
+        let deployment = &installation.deployments[0];
+        assert_eq!(deployment.version().unwrap(), Version::new(1, 0, 0, 0));
+        let application = deployment.application.as_ref().unwrap();
+        assert_eq!(application.folder.as_ref(), Some(&folder));
+
+        let statuses: Vec<(&str, &FileStatus)> = application
+            .files
+            .iter()
+            .map(|file| (file.name.as_str(), &file.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("MyApplication.exe.config", &FileStatus::Missing),
+                ("CardDataSource\\client_DMU.xml", &FileStatus::HashMismatch),
+                ("CardDataSource\\CARDNAME_STRING.xml", &FileStatus::Verified),
+                ("MyApplication.exe", &FileStatus::Verified),
+            ]
+        );
+        assert_eq!(deployment.problems().count(), 2);
+
+        // Manifests over the size limit are skipped before they are parsed
+        let limits = ParseLimits {
+            max_document_size: 64,
+            ..ParseLimits::default()
+        };
+        let installation = Installation::read_with_limits(apps.path(), &limits).unwrap();
+        assert!(installation.deployments.is_empty());
+        assert!(installation
+            .errors
+            .iter()
+            .any(|error| matches!(error.error, Error::DocumentTooLarge(64))));
+    }
+}
@@ -11,16 +11,151 @@
 
 use serde::Deserialize;
 
+pub use limits::ParseLimits;
+
+/// Errors returned by this library
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// XML error
+    #[error("XML error")]
+    XML(#[from] serde_xml_rs::Error),
+
+    /// The document's character encoding could not be detected or decoded
+    #[error("Encoding error {0}")]
+    Encoding(String),
+
+    /// The document is larger than the configured limit
+    #[error("Document exceeds the size limit of {0} bytes")]
+    DocumentTooLarge(usize),
+
+    /// The document's elements are nested deeper than the configured limit
+    #[error("Document exceeds the nesting limit of {0} elements")]
+    TooDeep(usize),
+
+    /// An element has more attributes than the configured limit
+    #[error("Element exceeds the limit of {0} attributes")]
+    TooManyAttributes(usize),
+
+    /// The document contains a document type declaration
+    #[error("Document type declarations are not permitted")]
+    DoctypeRejected,
+
+    /// The document declares entities, and entity expansion is disabled
+    #[error("Entity expansion is not permitted")]
+    EntityRejected,
+
+    /// A path taken from a manifest is not safe to write to disk
+    #[error("Unsafe path {path}: {reason}")]
+    UnsafePath {
+        /// The path as it appears in the manifest
+        path: String,
+        /// Why the path was rejected
+        reason: pathsafety::UnsafePathReason,
+    },
+
+    /// A version number is not in the format major.minor.build.revision
+    #[error("Invalid version {0}")]
+    InvalidVersion(String),
+
+    /// A certificate embedded in a signature could not be decoded
+    #[error("Certificate error {0}")]
+    Certificate(String),
+
+    /// The signing certificate does not match the manifest's publisher identity
+    #[error("Publisher mismatch: {0}")]
+    PublisherMismatch(String),
+
+    /// A signing key or its certificates could not be loaded or used
+    #[error("Key error {0}")]
+    Key(String),
+
+    /// A manifest signature does not match the manifest
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    /// A manifest is missing an element needed to update it
+    #[error("Manifest has no {0} element")]
+    MissingElement(String),
+
+    /// Build output could not be read
+    #[error("IO error")]
+    IO(#[from] std::io::Error),
+
+    /// A timestamp countersignature could not be decoded
+    #[error("Timestamp error {0}")]
+    Timestamp(String),
+
+    /// An `.appref-ms` shortcut could not be parsed, or does not match its
+    /// deployment
+    #[error("Invalid shortcut: {0}")]
+    InvalidShortcut(String),
+}
+
 /// Trait defined for all structures that can be deserialized from
 /// XML.
 pub trait FromXML<'de> {
-    /// Converts an XML string into a structure, or returns an error.
-    fn from_xml(contents: &str) -> Result<Self, serde_xml_rs::Error>
+    /// Converts an XML string into a structure, or returns an error. The
+    /// default [`ParseLimits`] are applied.
+    fn from_xml(contents: &str) -> Result<Self, Error>
+    where
+        Self: Sized + Deserialize<'de>,
+    {
+        Self::from_xml_with_limits(contents, &ParseLimits::default())
+    }
+
+    /// Converts an XML string into a structure, checking the document
+    /// against `limits` before it is deserialized. A leading byte order
+    /// mark is ignored.
+    fn from_xml_with_limits(contents: &str, limits: &ParseLimits) -> Result<Self, Error>
+    where
+        Self: Sized + Deserialize<'de>,
+    {
+        let contents = contents.trim_start_matches('\u{feff}');
+        limits.check(contents)?;
+        Ok(serde_xml_rs::from_str(contents)?)
+    }
+
+    /// Converts raw XML bytes into a structure, or returns an error.
+    ///
+    /// The encoding (UTF-8, UTF-16LE or UTF-16BE) is detected from the byte
+    /// order mark and the XML declaration, so callers do not need to
+    /// convert the document first. The default [`ParseLimits`] are applied.
+    fn from_xml_bytes(contents: &[u8]) -> Result<Self, Error>
+    where
+        Self: Sized + Deserialize<'de>,
+    {
+        Self::from_xml_bytes_with_limits(contents, &ParseLimits::default())
+    }
+
+    /// Converts raw XML bytes into a structure, checking the document
+    /// against `limits` before it is deserialized.
+    fn from_xml_bytes_with_limits(contents: &[u8], limits: &ParseLimits) -> Result<Self, Error>
     where
         Self: Sized + Deserialize<'de>,
     {
-        serde_xml_rs::from_str(contents)
+        limits.check_size(contents.len())?;
+        let contents = encoding::decode(contents)?;
+        Self::from_xml_with_limits(&contents, limits)
     }
 }
 
+pub mod applicationmanifest;
+pub mod appref;
+pub mod bootstrapper;
+pub mod certificate;
 pub mod deploymentmanifest;
+pub mod digest;
+pub mod dom;
+pub mod encoding;
+pub mod installation;
+pub mod limits;
+pub mod pathsafety;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod signature;
+pub mod signing;
+pub mod store;
+pub mod stream;
+pub mod timestamp;
+pub mod update;
+pub mod version;
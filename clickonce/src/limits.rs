@@ -0,0 +1,282 @@
+//! Limits applied when parsing untrusted manifests
+//!
+//! Manifests are usually fetched over plain HTTP, so a corrupt or malicious
+//! response must not be able to exhaust memory. [`ParseLimits`] bounds the
+//! size and shape of a document, and rejects document type declarations
+//! (and with them, entity expansion) unless explicitly allowed.
+use crate::Error;
+use std::io::Read;
+use xml::name::OwnedName;
+use xml::reader::{EventReader, ParserConfig2, XmlEvent};
+
+/// Limits applied to a document before it is deserialized
+#[derive(Debug, Clone)]
+pub struct ParseLimits {
+    /// Maximum size of the document, in bytes.
+    pub max_document_size: usize,
+
+    /// Maximum nesting depth of elements.
+    pub max_depth: usize,
+
+    /// Maximum number of attributes on a single element.
+    pub max_attributes: usize,
+
+    /// Whether a `<!DOCTYPE>` declaration is permitted.
+    pub allow_doctype: bool,
+
+    /// Whether entities declared in the document type declaration may be
+    /// expanded. Only relevant when `allow_doctype` is set.
+    pub allow_entity_expansion: bool,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_document_size: 64 * 1024 * 1024,
+            max_depth: 64,
+            max_attributes: 64,
+            allow_doctype: false,
+            allow_entity_expansion: false,
+        }
+    }
+}
+
+impl ParseLimits {
+    /// Checks that a document's size is within the limits.
+    pub fn check_size(&self, size: usize) -> Result<(), Error> {
+        if size > self.max_document_size {
+            Err(Error::DocumentTooLarge(self.max_document_size))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads all of `reader` into memory, failing as soon as the document
+    /// exceeds the size limit.
+    pub fn read_to_end<R: Read>(&self, reader: R) -> Result<Vec<u8>, Error> {
+        let mut contents = Vec::new();
+        let _ = reader
+            .take(self.max_document_size as u64 + 1)
+            .read_to_end(&mut contents)
+            .map_err(|e| Error::XML(serde_xml_rs::Error::Io { source: e }))?;
+        self.check_size(contents.len())?;
+        Ok(contents)
+    }
+
+    /// Creates an XML event reader for `source`, configured to bound the
+    /// memory used by entity expansion and attributes.
+    pub(crate) fn event_reader<R: Read>(&self, source: R) -> EventReader<R> {
+        EventReader::new_with_config(source, self.parser_config())
+    }
+
+    /// Parser configuration bounding the memory used by entity expansion
+    /// and attributes.
+    pub(crate) fn parser_config(&self) -> ParserConfig2 {
+        let mut config = ParserConfig2::new();
+        // Allow one more attribute than the limit so that it is reported
+        // as a limit error rather than a syntax error.
+        config.max_attributes = self.max_attributes.saturating_add(1);
+        config.max_data_length = self.max_document_size;
+        if !self.allow_entity_expansion {
+            config.max_entity_expansion_depth = 1;
+            config.max_entity_expansion_length = 0;
+        }
+        config
+    }
+
+    /// Returns the limit error for the document type declaration seen by
+    /// `reader`, if there is one. Used to explain parse failures caused by
+    /// the restrictions placed on entity expansion.
+    pub(crate) fn doctype_error<R: Read>(&self, reader: &EventReader<R>) -> Option<Error> {
+        let doctype = reader.doctype()?;
+        if !self.allow_doctype {
+            Some(Error::DoctypeRejected)
+        } else if !self.allow_entity_expansion && doctype.contains("<!ENTITY") {
+            Some(Error::EntityRejected)
+        } else {
+            None
+        }
+    }
+
+    /// Checks a single parser event against the limits. `depth` is the
+    /// nesting depth after the event has been applied.
+    pub(crate) fn check_event<R: Read>(
+        &self,
+        reader: &EventReader<R>,
+        event: &XmlEvent,
+        depth: usize,
+    ) -> Result<(), Error> {
+        if let XmlEvent::StartElement { attributes, .. } = event {
+            if let Some(e) = self.doctype_error(reader) {
+                return Err(e);
+            }
+            if depth > self.max_depth {
+                return Err(Error::TooDeep(self.max_depth));
+            }
+            if attributes.len() > self.max_attributes {
+                return Err(Error::TooManyAttributes(self.max_attributes));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a whole document against the limits.
+    pub fn check(&self, contents: &str) -> Result<(), Error> {
+        self.check_elements(contents, |_, _| {})
+    }
+
+    /// Checks a whole document against the limits, passing the depth and
+    /// name of each element to `visit` as it is read, counting the root
+    /// element as depth 1.
+    pub(crate) fn check_elements<F>(&self, contents: &str, mut visit: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, &OwnedName),
+    {
+        self.check_size(contents.len())?;
+
+        let mut reader = self.event_reader(contents.as_bytes());
+        let mut depth = 0;
+        loop {
+            let event = reader.next().map_err(|e| {
+                self.doctype_error(&reader)
+                    .unwrap_or_else(|| syntax_error(e))
+            })?;
+            match &event {
+                XmlEvent::StartElement { name, .. } => {
+                    depth += 1;
+                    visit(depth, name);
+                }
+                XmlEvent::EndElement { .. } => depth -= 1,
+                XmlEvent::EndDocument => return Ok(()),
+                _ => {}
+            }
+            self.check_event(&reader, &event, depth)?;
+        }
+    }
+}
+
+/// Reader that stops producing data once a document exceeds its size limit
+pub(crate) struct LimitedReader<R> {
+    inner: R,
+    remaining: usize,
+    exceeded: bool,
+}
+
+impl<R: Read> LimitedReader<R> {
+    pub(crate) fn new(inner: R, limit: usize) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            exceeded: false,
+        }
+    }
+
+    /// Whether the reader has been stopped by the size limit.
+    pub(crate) fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.remaining.saturating_add(1));
+        let read = self.inner.read(&mut buf[..len])?;
+        if read > self.remaining {
+            self.exceeded = true;
+            return Err(std::io::Error::other("document size limit exceeded"));
+        }
+        self.remaining -= read;
+        Ok(read)
+    }
+}
+
+pub(crate) fn syntax_error(e: xml::reader::Error) -> Error {
+    Error::XML(serde_xml_rs::Error::Syntax { source: e })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deploymentmanifest::DeploymentManifest;
+    use crate::FromXML;
+
+    #[test]
+    fn accepts_sample_manifests() {
+        let limits = ParseLimits::default();
+        for contents in &[
+            &include_bytes!("example/sample.application")[..],
+            &include_bytes!("example/sample.exe.manifest")[..],
+        ] {
+            let contents = crate::encoding::decode(contents).unwrap();
+            limits
+                .check(&contents)
+                .expect("Sample should be within limits");
+        }
+    }
+
+    #[test]
+    fn rejects_large_documents() {
+        let limits = ParseLimits {
+            max_document_size: 8,
+            ..ParseLimits::default()
+        };
+        assert!(matches!(
+            limits.check("<assembly/>"),
+            Err(Error::DocumentTooLarge(8))
+        ));
+        assert!(matches!(
+            limits.read_to_end(&b"<assembly/>"[..]),
+            Err(Error::DocumentTooLarge(8))
+        ));
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let limits = ParseLimits {
+            max_depth: 2,
+            ..ParseLimits::default()
+        };
+        assert!(limits.check("<a><b/></a>").is_ok());
+        assert!(matches!(
+            limits.check("<a><b><c/></b></a>"),
+            Err(Error::TooDeep(2))
+        ));
+    }
+
+    #[test]
+    fn rejects_too_many_attributes() {
+        let limits = ParseLimits {
+            max_attributes: 2,
+            ..ParseLimits::default()
+        };
+        assert!(limits.check("<a x='1' y='2'/>").is_ok());
+        assert!(matches!(
+            limits.check("<a x='1' y='2' z='3'/>"),
+            Err(Error::TooManyAttributes(2))
+        ));
+    }
+
+    #[test]
+    fn rejects_doctype_and_entities() {
+        let laughs = "<?xml version=\"1.0\"?>\
+            <!DOCTYPE lolz [<!ENTITY lol \"lol\"><!ENTITY lol2 \"&lol;&lol;&lol;&lol;\">]>\
+            <lolz>&lol2;</lolz>";
+        assert!(matches!(
+            ParseLimits::default().check(laughs),
+            Err(Error::DoctypeRejected)
+        ));
+
+        let limits = ParseLimits {
+            allow_doctype: true,
+            ..ParseLimits::default()
+        };
+        assert!(matches!(limits.check(laughs), Err(Error::EntityRejected)));
+        assert!(matches!(
+            DeploymentManifest::from_xml(laughs),
+            Err(Error::DoctypeRejected)
+        ));
+        assert!(limits
+            .check("<!DOCTYPE assembly><assembly>&amp;</assembly>")
+            .is_ok());
+    }
+}
@@ -0,0 +1,243 @@
+//! Validation of file names taken from manifests
+//!
+//! File names and codebases in manifests are attacker-controlled strings.
+//! Before a manifest file is written to disk its name must be checked so that
+//! it cannot escape the output directory or clash with another file on a
+//! case-insensitive file system.
+use crate::Error;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Device names that Windows reserves in every directory, with or without
+/// an extension. Windows also treats the superscript digits `¹`, `²` and
+/// `³` as port numbers.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$", "COM0", "COM1", "COM2", "COM3", "COM4",
+    "COM5", "COM6", "COM7", "COM8", "COM9", "COM¹", "COM²", "COM³", "LPT0", "LPT1", "LPT2", "LPT3",
+    "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+/// Characters other than separators and colons that Windows forbids in
+/// file names
+const FORBIDDEN_CHARACTERS: &[char] = &['<', '>', '"', '|', '?', '*'];
+
+/// Reasons a manifest path is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsafePathReason {
+    /// The path is empty, or has an empty component
+    Empty,
+    /// The path contains a `..` component
+    ParentDirectory,
+    /// The path is absolute
+    Absolute,
+    /// The path starts with a drive letter, or contains a colon
+    DriveLetter,
+    /// The path is a UNC path
+    Unc,
+    /// A component is a reserved Windows device name
+    ReservedName,
+    /// A component contains a control character, or a character Windows
+    /// forbids in file names
+    InvalidCharacter,
+    /// A component ends with a dot or space, which Windows strips
+    TrailingDotOrSpace,
+    /// The path differs only in case from the previously seen path it holds
+    CaseCollision(String),
+}
+
+impl fmt::Display for UnsafePathReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty path component"),
+            Self::ParentDirectory => write!(f, "parent directory component"),
+            Self::Absolute => write!(f, "absolute path"),
+            Self::DriveLetter => write!(f, "drive letter or stream name"),
+            Self::Unc => write!(f, "UNC path"),
+            Self::ReservedName => write!(f, "reserved Windows device name"),
+            Self::InvalidCharacter => write!(f, "control or forbidden character"),
+            Self::TrailingDotOrSpace => write!(f, "trailing dot or space"),
+            Self::CaseCollision(other) => write!(f, "collides with {}", other),
+        }
+    }
+}
+
+fn unsafe_path(path: &str, reason: UnsafePathReason) -> Error {
+    Error::UnsafePath {
+        path: path.to_string(),
+        reason,
+    }
+}
+
+fn is_reserved(component: &str) -> bool {
+    // Windows ignores trailing dots and spaces, and anything after the
+    // first dot, when matching device names.
+    let stem = component
+        .split('.')
+        .next()
+        .unwrap_or(component)
+        .trim_end_matches(' ');
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Validates a path from a manifest, returning it as a relative path
+/// suitable for joining onto an output directory.
+///
+/// Both `\` and `/` are treated as separators, as manifests are written on
+/// Windows.
+pub fn validate(path: &str) -> Result<PathBuf, Error> {
+    if path.starts_with("\\\\") || path.starts_with("//") {
+        return Err(unsafe_path(path, UnsafePathReason::Unc));
+    }
+    if path.starts_with('\\') || path.starts_with('/') {
+        return Err(unsafe_path(path, UnsafePathReason::Absolute));
+    }
+    if path.contains(':') {
+        return Err(unsafe_path(path, UnsafePathReason::DriveLetter));
+    }
+    if path
+        .chars()
+        .any(|c| c.is_control() || FORBIDDEN_CHARACTERS.contains(&c))
+    {
+        return Err(unsafe_path(path, UnsafePathReason::InvalidCharacter));
+    }
+
+    let mut relative = PathBuf::new();
+    for component in path.split(['\\', '/']) {
+        match component {
+            "" => return Err(unsafe_path(path, UnsafePathReason::Empty)),
+            "." => continue,
+            ".." => return Err(unsafe_path(path, UnsafePathReason::ParentDirectory)),
+            _ if component.ends_with(['.', ' ']) => {
+                return Err(unsafe_path(path, UnsafePathReason::TrailingDotOrSpace))
+            }
+            _ if is_reserved(component) => {
+                return Err(unsafe_path(path, UnsafePathReason::ReservedName))
+            }
+            _ => relative.push(component),
+        }
+    }
+
+    if relative.as_os_str().is_empty() {
+        return Err(unsafe_path(path, UnsafePathReason::Empty));
+    }
+    Ok(relative)
+}
+
+/// Validates a path from a manifest and joins it onto `root`.
+pub fn join(root: &Path, path: &str) -> Result<PathBuf, Error> {
+    Ok(root.join(validate(path)?))
+}
+
+/// A set of manifest paths which must not collide with each other on a
+/// case-insensitive file system.
+#[derive(Debug, Default)]
+pub struct SafePaths {
+    seen: HashMap<String, String>,
+}
+
+impl SafePaths {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates a path and records it, rejecting it if it or any of its
+    /// parent directories collide with a path already in the set. Adding the
+    /// same path twice is permitted.
+    pub fn insert(&mut self, path: &str) -> Result<PathBuf, Error> {
+        let relative = validate(path)?;
+
+        let mut prefix = PathBuf::new();
+        for component in relative.iter() {
+            prefix.push(component);
+            let original = prefix.to_string_lossy().into_owned();
+            let existing = self
+                .seen
+                .entry(original.to_lowercase())
+                .or_insert_with(|| original.clone());
+            if *existing != original {
+                return Err(unsafe_path(
+                    path,
+                    UnsafePathReason::CaseCollision(existing.clone()),
+                ));
+            }
+        }
+        Ok(relative)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(path: &str) -> UnsafePathReason {
+        match validate(path) {
+            Err(Error::UnsafePath { reason, .. }) => reason,
+            other => panic!("Expected {} to be rejected, got {:?}", path, other),
+        }
+    }
+
+    #[test]
+    fn accepts_relative_paths() {
+        assert_eq!(
+            validate("CardDataSource\\client_DMU.xml").unwrap(),
+            Path::new("CardDataSource").join("client_DMU.xml")
+        );
+        assert_eq!(validate("./a/b.xml").unwrap(), Path::new("a").join("b.xml"));
+        assert!(validate("console.xml").is_ok());
+    }
+
+    #[test]
+    fn rejects_unsafe_paths() {
+        assert_eq!(reason("..\\evil.dll"), UnsafePathReason::ParentDirectory);
+        assert_eq!(reason("a/../../evil"), UnsafePathReason::ParentDirectory);
+        assert_eq!(reason("/etc/passwd"), UnsafePathReason::Absolute);
+        assert_eq!(reason("\\Windows"), UnsafePathReason::Absolute);
+        assert_eq!(reason("C:\\Windows"), UnsafePathReason::DriveLetter);
+        assert_eq!(reason("c:evil"), UnsafePathReason::DriveLetter);
+        assert_eq!(reason("\\\\server\\share"), UnsafePathReason::Unc);
+        assert_eq!(reason("data\\CON"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("nul.txt"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("Lpt1 .xml"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("com0"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("LPT0.log"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("COM¹.txt"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("data\\lpt³"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("CONIN$"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("conout$.xml"), UnsafePathReason::ReservedName);
+        assert_eq!(reason("data.\\a.xml"), UnsafePathReason::TrailingDotOrSpace);
+        assert_eq!(reason("a.xml "), UnsafePathReason::TrailingDotOrSpace);
+        assert_eq!(reason("..."), UnsafePathReason::TrailingDotOrSpace);
+        assert_eq!(reason("a\\\\b"), UnsafePathReason::Empty);
+        assert_eq!(reason(""), UnsafePathReason::Empty);
+        assert_eq!(reason("a\0b"), UnsafePathReason::InvalidCharacter);
+        for path in &["a<b", "a>b", "a\"b", "a|b", "a?b", "data\\*.xml"] {
+            assert_eq!(reason(path), UnsafePathReason::InvalidCharacter, "{}", path);
+        }
+    }
+
+    #[test]
+    fn rejects_case_collisions() {
+        let mut paths = SafePaths::new();
+        assert!(paths.insert("Data\\Cards.xml").is_ok());
+        assert!(paths.insert("Data/Cards.xml").is_ok());
+        assert!(paths.insert("Data\\Sets.xml").is_ok());
+        assert!(matches!(
+            paths.insert("data\\cards.XML"),
+            Err(Error::UnsafePath {
+                reason: UnsafePathReason::CaseCollision(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            paths.insert("DATA\\Other.xml"),
+            Err(Error::UnsafePath {
+                reason: UnsafePathReason::CaseCollision(_),
+                ..
+            })
+        ));
+    }
+}
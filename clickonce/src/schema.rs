@@ -0,0 +1,81 @@
+//! JSON Schema documents for the serialized manifest models
+//!
+//! The schemas describe the JSON produced by serializing the models with
+//! the `serialize` feature. Copies are checked in under `schema/` for
+//! consumers that do not build this crate; the tests fail if they fall out of
+//! date, and running them with `UPDATE_SCHEMA=1` rewrites them.
+use crate::applicationmanifest::ApplicationManifest;
+use crate::deploymentmanifest::DeploymentManifest;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+/// The schema of a serialized [`DeploymentManifest`].
+pub fn deployment_manifest() -> RootSchema {
+    schema_for!(DeploymentManifest)
+}
+
+/// The schema of a serialized [`ApplicationManifest`].
+pub fn application_manifest() -> RootSchema {
+    schema_for!(ApplicationManifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FromXML;
+    use std::path::Path;
+
+    fn check_schema(schema: RootSchema, file: &str) {
+        let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("schema")
+            .join(file);
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            checked_in == generated,
+            "{} is out of date; rerun the tests with UPDATE_SCHEMA=1",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn deployment_manifest_schema_is_current() {
+        check_schema(deployment_manifest(), "deploymentmanifest.schema.json");
+    }
+
+    #[test]
+    fn application_manifest_schema_is_current() {
+        check_schema(application_manifest(), "applicationmanifest.schema.json");
+    }
+
+    fn check_valid(schema: RootSchema, instance: serde_json::Value) {
+        let schema = serde_json::to_value(schema).unwrap();
+        let compiled = jsonschema::JSONSchema::compile(&schema).unwrap();
+        if let Err(errors) = compiled.validate(&instance) {
+            let errors: Vec<String> = errors.map(|error| error.to_string()).collect();
+            panic!("Sample does not match schema: {:?}", errors);
+        };
+    }
+
+    #[test]
+    fn samples_match_schemas() {
+        let manifest =
+            DeploymentManifest::from_xml_bytes(include_bytes!("example/sample.application"))
+                .unwrap();
+        check_valid(
+            deployment_manifest(),
+            serde_json::to_value(&manifest).unwrap(),
+        );
+
+        let manifest =
+            ApplicationManifest::from_xml_bytes(include_bytes!("example/sample.exe.manifest"))
+                .unwrap();
+        check_valid(
+            application_manifest(),
+            serde_json::to_value(&manifest).unwrap(),
+        );
+    }
+}
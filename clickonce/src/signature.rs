@@ -0,0 +1,545 @@
+//! Models for the XML digital signature embedded in ClickOnce manifests
+//!
+//! Signed manifests carry a strong name `<Signature>` whose `<KeyInfo>`
+//! contains an Authenticode license. The license's issuer is a second
+//! `<Signature>` holding the publisher's X.509 certificate chain.
+//!
+//! Defined at https://www.w3.org/TR/xmldsig-core/ and
+//! https://docs.microsoft.com/en-us/dotnet/framework/tools/mage-exe-manifest-generation-and-editing-tool
+use crate::certificate::Certificate;
+use crate::deploymentmanifest::{
+    DsigDigestMethod, DsigDigestValue, DsigTransform, PublisherIdentity,
+};
+use crate::timestamp::Timestamp;
+use crate::Error;
+use serde::Deserialize;
+
+/// Model for <Signature> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Signature {
+    /// Attribute. Optional. `StrongNameSignature` or `AuthenticodeSignature`.
+    #[serde(alias = "Id")]
+    pub id: Option<String>,
+
+    /// Child. Required. The information that is signed.
+    #[serde(alias = "SignedInfo")]
+    pub signed_info: SignedInfo,
+
+    /// Child. Required. The base64 encoded signature.
+    #[serde(alias = "SignatureValue")]
+    pub signature_value: Base64Text,
+
+    /// Child. Optional. The key used to create the signature.
+    #[serde(alias = "KeyInfo")]
+    pub key_info: Option<KeyInfo>,
+
+    /// Child. Optional. Additional signed data, such as a timestamp.
+    #[serde(alias = "Object")]
+    pub object: Option<Object>,
+}
+
+/// Model for <SignedInfo> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct SignedInfo {
+    /// Child. Required. The algorithm used to canonicalize the signed information.
+    #[serde(alias = "CanonicalizationMethod")]
+    pub canonicalization_method: Algorithm,
+
+    /// Child. Required. The algorithm used to create the signature.
+    #[serde(alias = "SignatureMethod")]
+    pub signature_method: Algorithm,
+
+    /// Children. Required. The content that is signed.
+    #[serde(alias = "Reference")]
+    pub reference: Vec<Reference>,
+}
+
+/// Model for elements that only carry an `Algorithm` attribute
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Algorithm {
+    /// Attribute. Required. The URI of the algorithm.
+    #[serde(alias = "Algorithm")]
+    pub algorithm: String,
+}
+
+/// Model for <Reference> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Reference {
+    /// Attribute. Optional. The content that is signed; empty for the whole document.
+    #[serde(alias = "URI")]
+    pub uri: Option<String>,
+
+    /// Child. Optional. The transforms applied before the digest is computed.
+    #[serde(alias = "Transforms")]
+    pub transforms: Option<Transforms>,
+
+    /// Child. Required. The algorithm used to compute the digest.
+    #[serde(alias = "DigestMethod")]
+    pub digest_method: DsigDigestMethod,
+
+    /// Child. Required. The digest of the referenced content.
+    #[serde(alias = "DigestValue")]
+    pub digest_value: DsigDigestValue,
+}
+
+/// Model for <Transforms> element of a signature reference
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Transforms {
+    /// Children. Required.
+    #[serde(alias = "Transform")]
+    pub transform: Vec<DsigTransform>,
+}
+
+/// Model for elements containing base64 encoded text
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Base64Text {
+    /// Text. Required.
+    #[serde(rename(deserialize = "$value"))]
+    #[cfg_attr(feature = "schema", schemars(rename = "value"))]
+    pub value: String,
+}
+
+impl Base64Text {
+    /// Decodes the text, ignoring any whitespace.
+    pub fn decode(&self) -> Result<Vec<u8>, Error> {
+        let compact: String = self.value.chars().filter(|c| !c.is_whitespace()).collect();
+        base64::decode(compact).map_err(|e| Error::Certificate(format!("invalid base64: {}", e)))
+    }
+}
+
+/// Model for <KeyInfo> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct KeyInfo {
+    /// Attribute. Optional.
+    #[serde(alias = "Id")]
+    pub id: Option<String>,
+
+    /// Child. Optional. The public key used to create the signature.
+    #[serde(alias = "KeyValue")]
+    pub key_value: Option<KeyValue>,
+
+    /// Child. Optional. The Authenticode license of a strong name signature.
+    #[serde(alias = "RelData")]
+    pub rel_data: Option<RelData>,
+
+    /// Child. Optional. The certificate chain of an Authenticode signature.
+    #[serde(alias = "X509Data")]
+    pub x509_data: Option<X509Data>,
+}
+
+/// Model for <KeyValue> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct KeyValue {
+    /// Child. Required.
+    #[serde(alias = "RSAKeyValue")]
+    pub rsa_key_value: RSAKeyValue,
+}
+
+/// Model for <RSAKeyValue> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct RSAKeyValue {
+    /// Child. Required. The RSA modulus.
+    #[serde(alias = "Modulus")]
+    pub modulus: Base64Text,
+
+    /// Child. Required. The RSA public exponent.
+    #[serde(alias = "Exponent")]
+    pub exponent: Base64Text,
+}
+
+/// Model for <msrel:RelData> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct RelData {
+    /// Child. Required.
+    pub license: License,
+}
+
+/// Model for <r:license> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct License {
+    /// Child. Required. What the license grants.
+    pub grant: Grant,
+
+    /// Child. Required. Who issued the license.
+    pub issuer: Issuer,
+}
+
+/// Model for <r:grant> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Grant {
+    /// Child. Optional. The publisher named by the license.
+    #[serde(alias = "AuthenticodePublisher")]
+    pub authenticode_publisher: Option<AuthenticodePublisher>,
+}
+
+/// Model for <as:AuthenticodePublisher> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct AuthenticodePublisher {
+    /// Child. Required. The subject name of the publisher's certificate.
+    #[serde(alias = "X509SubjectName")]
+    pub x509_subject_name: Base64Text,
+}
+
+/// Model for <r:issuer> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Issuer {
+    /// Child. Required. The Authenticode signature over the license.
+    #[serde(alias = "Signature")]
+    pub signature: Box<Signature>,
+}
+
+/// Model for <Object> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct Object {
+    /// Child. Optional. The base64 encoded timestamp countersignature.
+    #[serde(alias = "Timestamp")]
+    pub timestamp: Option<Base64Text>,
+}
+
+/// Model for <X509Data> element
+#[derive(Debug, Deserialize)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize),
+    serde(rename_all(serialize = "camelCase"))
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename_all = "camelCase")
+)]
+pub struct X509Data {
+    /// Children. Required. Base64 encoded DER certificates, signer first.
+    #[serde(alias = "X509Certificate")]
+    pub x509_certificate: Vec<Base64Text>,
+}
+
+impl Signature {
+    /// The Authenticode signature, either this signature or the one issuing
+    /// its license.
+    pub fn authenticode(&self) -> &Signature {
+        self.key_info
+            .as_ref()
+            .and_then(|k| k.rel_data.as_ref())
+            .map(|r| &*r.license.issuer.signature)
+            .unwrap_or(self)
+    }
+
+    /// The subject name the Authenticode license grants to, if any.
+    pub fn publisher_subject_name(&self) -> Option<&str> {
+        self.key_info
+            .as_ref()?
+            .rel_data
+            .as_ref()?
+            .license
+            .grant
+            .authenticode_publisher
+            .as_ref()
+            .map(|p| p.x509_subject_name.value.trim())
+    }
+
+    /// Parses the embedded X.509 certificate chain, signer first.
+    pub fn certificates(&self) -> Result<Vec<Certificate>, Error> {
+        match self
+            .authenticode()
+            .key_info
+            .as_ref()
+            .and_then(|k| k.x509_data.as_ref())
+        {
+            Some(data) => data
+                .x509_certificate
+                .iter()
+                .map(|c| Certificate::from_der(&c.decode()?))
+                .collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Parses the certificate that signed the manifest, identified by the
+    /// license's publisher subject name and falling back to the first
+    /// certificate in the chain.
+    pub fn publisher_certificate(&self) -> Result<Option<Certificate>, Error> {
+        let mut certificates = self.certificates()?;
+        let position = self
+            .publisher_subject_name()
+            .and_then(|name| {
+                certificates
+                    .iter()
+                    .position(|c| crate::certificate::names_match(&c.subject, name))
+            })
+            .unwrap_or(0);
+        if certificates.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(certificates.swap_remove(position)))
+        }
+    }
+
+    /// Decodes the timestamp countersignature of the Authenticode
+    /// signature, if any.
+    pub fn timestamp(&self) -> Result<Option<Timestamp>, Error> {
+        match self
+            .authenticode()
+            .object
+            .as_ref()
+            .and_then(|o| o.timestamp.as_ref())
+        {
+            Some(timestamp) => Ok(Some(Timestamp::from_der(&timestamp.decode()?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Checks the signing certificate against the manifest's
+    /// `<publisherIdentity>`: the subject must match its `name`, and the
+    /// issuer's public key must match its `issuerKeyHash`.
+    ///
+    /// Returns the signing certificate.
+    pub fn verify_publisher(&self, identity: &PublisherIdentity) -> Result<Certificate, Error> {
+        let chain = self.certificates()?;
+        let certificate = self
+            .publisher_certificate()?
+            .ok_or_else(|| Error::PublisherMismatch("no certificate in signature".to_string()))?;
+
+        if !crate::certificate::names_match(&certificate.subject, &identity.name) {
+            return Err(Error::PublisherMismatch(format!(
+                "certificate subject {} does not match publisher {}",
+                certificate.subject, identity.name
+            )));
+        }
+
+        let issuer = if certificate.is_self_issued() {
+            Some(&certificate)
+        } else {
+            chain.iter().find(|c| c.subject == certificate.issuer)
+        };
+        match issuer {
+            Some(issuer)
+                if issuer
+                    .public_key_hash()
+                    .eq_ignore_ascii_case(&identity.issuer_key_hash) =>
+            {
+                Ok(certificate)
+            }
+            Some(issuer) => Err(Error::PublisherMismatch(format!(
+                "issuer key hash {} does not match {}",
+                issuer.public_key_hash(),
+                identity.issuer_key_hash
+            ))),
+            None => Err(Error::PublisherMismatch(format!(
+                "issuer {} is not in the certificate chain",
+                certificate.issuer
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deploymentmanifest::DeploymentManifest;
+    use crate::{Error, FromXML};
+
+    fn manifest() -> DeploymentManifest {
+        DeploymentManifest::from_xml_bytes(include_bytes!("example/sample.application"))
+            .expect("Failure parsing manifest")
+    }
+
+    #[test]
+    fn extracts_certificate_chain() {
+        let manifest = manifest();
+        let signature = manifest.assembly.signature.as_ref().unwrap();
+        assert_eq!(signature.id.as_deref(), Some("StrongNameSignature"));
+        assert_eq!(
+            signature.authenticode().id.as_deref(),
+            Some("AuthenticodeSignature")
+        );
+
+        let chain = signature.certificates().unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[1].subject, "O=Example Corp, CN=Example Root CA");
+
+        let publisher = signature.publisher_certificate().unwrap().unwrap();
+        assert_eq!(
+            publisher.subject,
+            "CN=Example Corp, O=Example Corp, L=Renton, S=Washington, C=US"
+        );
+        assert_eq!(publisher.issuer, chain[1].subject);
+        assert_eq!(publisher.serial_number, "1A2B3C4D");
+        assert_eq!(
+            publisher.thumbprint,
+            "65EF79B694FEC7DD4F069D860D099FBC775A71E5"
+        );
+        assert_eq!(publisher.not_before.year(), 2022);
+        assert_eq!(publisher.not_after.year(), 2032);
+    }
+
+    #[test]
+    fn verifies_publisher_identity() {
+        let mut manifest = manifest();
+        let signature = manifest.assembly.signature.take().unwrap();
+        let identity = manifest.assembly.publisher_identity.as_mut().unwrap();
+        let certificate = signature.verify_publisher(identity).unwrap();
+        assert_eq!(certificate.serial_number, "1A2B3C4D");
+
+        identity.issuer_key_hash = "0000000000000000000000000000000000000000".to_string();
+        assert!(matches!(
+            signature.verify_publisher(identity),
+            Err(Error::PublisherMismatch(_))
+        ));
+
+        identity.name = "CN=Someone Else".to_string();
+        assert!(matches!(
+            signature.verify_publisher(identity),
+            Err(Error::PublisherMismatch(_))
+        ));
+    }
+}
@@ -0,0 +1,600 @@
+//! Signing manifests with an RSA key, as `mage -sign` does
+//!
+//! A signed manifest ends with a strong name `<Signature>` over the whole
+//! document. Its `<KeyInfo>` carries an Authenticode license naming the
+//! publisher and the manifest's hash, which is signed in turn with the
+//! publisher's certificate. The same RSA key makes both signatures.
+use crate::certificate::{to_hex, Certificate};
+use crate::digest::DigestAlgorithm;
+use crate::dom::{Element, Node};
+use crate::Error;
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey, EncodeRsaPublicKey};
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::traits::PublicKeyParts;
+use rsa::{BigUint, Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use sha1::{Digest, Sha1};
+use std::convert::TryFrom;
+
+const ASM_V1: &str = "urn:schemas-microsoft-com:asm.v1";
+const ASM_V2: &str = "urn:schemas-microsoft-com:asm.v2";
+const DSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+const REL_DATA: &str = "http://schemas.microsoft.com/windows/rel/2005/reldata";
+const LICENSE: &str = "urn:mpeg:mpeg21:2003:01-REL-R-NS";
+const AUTHENTICODE: &str = "http://schemas.microsoft.com/windows/pki/2005/Authenticode";
+const EXCLUSIVE_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+/// An RSA private key and the certificate chain of its publisher
+pub struct SigningKey {
+    key: RsaPrivateKey,
+    certificates: Vec<Certificate>,
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("certificates", &self.certificates)
+            .finish()
+    }
+}
+
+fn key_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::Key(e.to_string())
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidSignature(message.to_string())
+}
+
+impl SigningKey {
+    /// Pairs a key with its certificates, putting the certificate for the
+    /// key first.
+    fn new(key: RsaPrivateKey, mut certificates: Vec<Certificate>) -> Result<Self, Error> {
+        let public_key = key.to_public_key().to_pkcs1_der().map_err(key_error)?;
+        let position = certificates
+            .iter()
+            .position(|c| c.public_key == public_key.as_bytes())
+            .ok_or_else(|| Error::Key("no certificate matches the private key".to_string()))?;
+        let certificate = certificates.remove(position);
+        certificates.insert(0, certificate);
+        Ok(Self { key, certificates })
+    }
+
+    /// Loads a PEM bundle containing an unencrypted RSA private key (PKCS #1
+    /// or PKCS #8), the publisher's certificate and optionally the rest of
+    /// its chain.
+    pub fn from_pem(pem: &str) -> Result<Self, Error> {
+        let mut key = None;
+        let mut certificates = Vec::new();
+        for (label, contents) in pem_blocks(pem)? {
+            match label {
+                "CERTIFICATE" => certificates.push(Certificate::from_der(&contents)?),
+                "PRIVATE KEY" => {
+                    key = Some(RsaPrivateKey::from_pkcs8_der(&contents).map_err(key_error)?)
+                }
+                "RSA PRIVATE KEY" => {
+                    key = Some(RsaPrivateKey::from_pkcs1_der(&contents).map_err(key_error)?)
+                }
+                label => return Err(Error::Key(format!("unsupported PEM block {}", label))),
+            }
+        }
+        let key = key.ok_or_else(|| Error::Key("no private key in PEM".to_string()))?;
+        Self::new(key, certificates)
+    }
+
+    /// Loads the first private key and its certificate chain from a PKCS #12
+    /// (.pfx) file.
+    pub fn from_pkcs12(der: &[u8], password: &str) -> Result<Self, Error> {
+        let store = p12_keystore::KeyStore::from_pkcs12(der, password).map_err(key_error)?;
+        let (_, chain) = store
+            .private_key_chain()
+            .ok_or_else(|| Error::Key("no private key in PKCS #12 file".to_string()))?;
+        let key = RsaPrivateKey::from_pkcs8_der(chain.key()).map_err(key_error)?;
+        let certificates = chain
+            .chain()
+            .iter()
+            .map(|c| Certificate::from_der(c.as_der()))
+            .collect::<Result<_, _>>()?;
+        Self::new(key, certificates)
+    }
+
+    /// The publisher's certificate.
+    pub fn certificate(&self) -> &Certificate {
+        &self.certificates[0]
+    }
+
+    /// The certificates written to the Authenticode signature, publisher
+    /// first.
+    pub fn certificates(&self) -> &[Certificate] {
+        &self.certificates
+    }
+
+    /// The public key token of the key, as lowercase hexadecimal: the last
+    /// eight bytes, reversed, of the SHA-1 hash of the strong name public
+    /// key blob.
+    pub fn public_key_token(&self) -> Result<String, Error> {
+        let mut modulus = self.key.n().to_bytes_le();
+        modulus.resize(self.key.size(), 0);
+        let bits = u32::try_from(modulus.len() * 8).map_err(key_error)?;
+        let exponent = self.key.e().to_bytes_le();
+        if exponent.len() > 4 {
+            return Err(Error::Key("public exponent is too large".to_string()));
+        }
+        let exponent = exponent
+            .iter()
+            .rev()
+            .fold(0u32, |value, &b| (value << 8) | u32::from(b));
+
+        let mut blob = Vec::with_capacity(32 + modulus.len());
+        blob.extend_from_slice(&0x2400u32.to_le_bytes()); // CALG_RSA_SIGN
+        blob.extend_from_slice(&0x8004u32.to_le_bytes()); // CALG_SHA1
+        blob.extend_from_slice(&(20 + bits / 8).to_le_bytes());
+        blob.extend_from_slice(&[0x06, 0x02, 0x00, 0x00]); // PUBLICKEYBLOB
+        blob.extend_from_slice(&0x2400u32.to_le_bytes());
+        blob.extend_from_slice(b"RSA1");
+        blob.extend_from_slice(&bits.to_le_bytes());
+        blob.extend_from_slice(&exponent.to_le_bytes());
+        blob.extend_from_slice(&modulus);
+
+        let hash = Sha1::digest(&blob);
+        let token: Vec<u8> = hash.iter().rev().take(8).copied().collect();
+        Ok(to_hex(&token, false))
+    }
+
+    /// The `issuerKeyHash` of the publisher's certificate: the hash of its
+    /// issuer's public key, which must be in the chain unless the
+    /// certificate is self-issued.
+    pub fn issuer_key_hash(&self) -> Result<String, Error> {
+        let certificate = self.certificate();
+        if certificate.is_self_issued() {
+            return Ok(certificate.public_key_hash());
+        }
+        self.certificates
+            .iter()
+            .find(|c| c.subject == certificate.issuer)
+            .map(Certificate::public_key_hash)
+            .ok_or_else(|| {
+                Error::Key(format!(
+                    "issuer certificate {} is not included",
+                    certificate.issuer
+                ))
+            })
+    }
+
+    fn sign(&self, algorithm: DigestAlgorithm, data: &[u8]) -> Result<Vec<u8>, Error> {
+        self.key
+            .sign(padding(algorithm), &algorithm.digest(data))
+            .map_err(key_error)
+    }
+}
+
+/// Splits a PEM file into its labelled, base64 decoded blocks.
+fn pem_blocks(pem: &str) -> Result<Vec<(&str, Vec<u8>)>, Error> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(&str, String)> = None;
+    for line in pem.lines().map(str::trim) {
+        if let Some(label) = line
+            .strip_prefix("-----BEGIN ")
+            .and_then(|l| l.strip_suffix("-----"))
+        {
+            current = Some((label, String::new()));
+        } else if line.starts_with("-----END ") {
+            let (label, contents) = current
+                .take()
+                .ok_or_else(|| Error::Key("unexpected end of PEM block".to_string()))?;
+            blocks.push((label, base64::decode(contents).map_err(key_error)?));
+        } else if let Some((_, contents)) = current.as_mut() {
+            contents.push_str(line);
+        }
+    }
+    Ok(blocks)
+}
+
+fn padding(algorithm: DigestAlgorithm) -> Pkcs1v15Sign {
+    match algorithm {
+        DigestAlgorithm::Sha1 => Pkcs1v15Sign::new::<Sha1>(),
+        DigestAlgorithm::Sha256 => Pkcs1v15Sign::new::<sha2::Sha256>(),
+    }
+}
+
+/// Hexadecimal in the reversed byte order used by `ManifestInformation`.
+fn reversed_hex(bytes: &[u8]) -> String {
+    let reversed: Vec<u8> = bytes.iter().rev().copied().collect();
+    to_hex(&reversed, false)
+}
+
+fn child<'a>(
+    element: &'a Element,
+    namespace: &str,
+    local_name: &str,
+) -> Result<&'a Element, Error> {
+    element
+        .child(namespace, local_name)
+        .ok_or_else(|| Error::InvalidSignature(format!("missing {} element", local_name)))
+}
+
+fn child_mut<'a>(
+    element: &'a mut Element,
+    namespace: &str,
+    local_name: &str,
+) -> Result<&'a mut Element, Error> {
+    element
+        .child_mut(namespace, local_name)
+        .ok_or_else(|| Error::InvalidSignature(format!("missing {} element", local_name)))
+}
+
+/// The template for a signature, formatted as mage formats it. The
+/// Authenticode license is added to its `<KeyInfo>` separately.
+fn signature_template(id: &str, algorithm: DigestAlgorithm) -> Result<Element, Error> {
+    let template = format!(
+        "<Signature Id=\"{id}\" xmlns=\"{dsig}\">\n\
+         <SignedInfo>\n\
+         <CanonicalizationMethod Algorithm=\"{c14n}\" />\n\
+         <SignatureMethod Algorithm=\"{signature}\" />\n\
+         <Reference URI=\"\">\n\
+         <Transforms>\n\
+         <Transform Algorithm=\"{dsig}enveloped-signature\" />\n\
+         <Transform Algorithm=\"{c14n}\" />\n\
+         </Transforms>\n\
+         <DigestMethod Algorithm=\"{digest}\" />\n\
+         <DigestValue></DigestValue>\n\
+         </Reference>\n\
+         </SignedInfo>\n\
+         <SignatureValue></SignatureValue>\n\
+         <KeyInfo>\n\
+         <KeyValue>\n\
+         <RSAKeyValue>\n\
+         <Modulus></Modulus>\n\
+         <Exponent></Exponent>\n\
+         </RSAKeyValue>\n\
+         </KeyValue>\n\
+         </KeyInfo>\n\
+         </Signature>",
+        id = id,
+        dsig = DSIG,
+        c14n = EXCLUSIVE_C14N,
+        signature = algorithm.signature_uri(),
+        digest = algorithm.uri(),
+    );
+    Element::parse(template.as_bytes())
+}
+
+/// The template for the Authenticode license, wrapped in `<msrel:RelData>`.
+fn license_template() -> Result<Element, Error> {
+    let template = format!(
+        "<msrel:RelData xmlns:msrel=\"{rel_data}\">\n\
+         <r:license xmlns:r=\"{license}\" xmlns:as=\"{authenticode}\">\n\
+         <r:grant>\n\
+         <as:ManifestInformation Hash=\"\" Description=\"\" Url=\"\">\n\
+         <as:assemblyIdentity />\n\
+         </as:ManifestInformation>\n\
+         <as:SignedBy />\n\
+         <as:AuthenticodePublisher>\n\
+         <as:X509SubjectName></as:X509SubjectName>\n\
+         </as:AuthenticodePublisher>\n\
+         </r:grant>\n\
+         <r:issuer></r:issuer>\n\
+         </r:license>\n\
+         </msrel:RelData>",
+        rel_data = REL_DATA,
+        license = LICENSE,
+        authenticode = AUTHENTICODE,
+    );
+    Element::parse(template.as_bytes())
+}
+
+/// Fills in a signature template: the digest of `signed`, then the
+/// signature over `<SignedInfo>` and the public key.
+fn complete_signature(
+    signature: &mut Element,
+    digest: &[u8],
+    key: &SigningKey,
+    algorithm: DigestAlgorithm,
+) -> Result<(), Error> {
+    let signed_info = child_mut(signature, DSIG, "SignedInfo")?;
+    child_mut(
+        child_mut(signed_info, DSIG, "Reference")?,
+        DSIG,
+        "DigestValue",
+    )?
+    .set_text(&base64::encode(digest));
+    let canonical = child(signature, DSIG, "SignedInfo")?.canonicalize();
+    child_mut(signature, DSIG, "SignatureValue")?
+        .set_text(&base64::encode(key.sign(algorithm, canonical.as_bytes())?));
+
+    let key_info = child_mut(signature, DSIG, "KeyInfo")?;
+    let rsa_key_value = child_mut(child_mut(key_info, DSIG, "KeyValue")?, DSIG, "RSAKeyValue")?;
+    child_mut(rsa_key_value, DSIG, "Modulus")?.set_text(&base64::encode(key.key.n().to_bytes_be()));
+    child_mut(rsa_key_value, DSIG, "Exponent")?
+        .set_text(&base64::encode(key.key.e().to_bytes_be()));
+    Ok(())
+}
+
+/// Signs a manifest in place.
+///
+/// Any existing signature and `<publisherIdentity>` are replaced, and the
+/// `publicKeyToken` of the manifest's `<assemblyIdentity>` is set to that of
+/// `key`.
+pub fn sign_element(
+    root: &mut Element,
+    key: &SigningKey,
+    algorithm: DigestAlgorithm,
+) -> Result<(), Error> {
+    root.remove_children(DSIG, "Signature");
+    root.remove_children(ASM_V1, "publisherIdentity");
+    root.remove_children(ASM_V2, "publisherIdentity");
+
+    let identity = child_mut(root, ASM_V1, "assemblyIdentity")
+        .map_err(|_| Error::Key("manifest has no assemblyIdentity".to_string()))?;
+    identity.set_attribute("publicKeyToken", &key.public_key_token()?);
+    let identity = identity.clone();
+
+    let mut publisher = root.create_element(ASM_V2, "publisherIdentity");
+    publisher.set_attribute("name", &key.certificate().subject);
+    publisher.set_attribute("issuerKeyHash", &key.issuer_key_hash()?);
+    root.children.push(Node::Element(publisher));
+
+    let manifest_digest = algorithm.digest(root.canonicalize().as_bytes());
+
+    // The Authenticode license names the publisher and the manifest, and is
+    // signed with the publisher's certificate.
+    let mut rel_data = license_template()?;
+    {
+        let license = child_mut(&mut rel_data, LICENSE, "license")?;
+        let grant = child_mut(license, LICENSE, "grant")?;
+        let information = child_mut(grant, AUTHENTICODE, "ManifestInformation")?;
+        information.set_attribute("Hash", &reversed_hex(&manifest_digest));
+        let license_identity = child_mut(information, AUTHENTICODE, "assemblyIdentity")?;
+        license_identity.attributes = identity.attributes;
+        license_identity
+            .namespaces
+            .push((String::new(), ASM_V1.to_string()));
+        child_mut(
+            child_mut(grant, AUTHENTICODE, "AuthenticodePublisher")?,
+            AUTHENTICODE,
+            "X509SubjectName",
+        )?
+        .set_text(&key.certificate().subject);
+
+        let license_digest = algorithm.digest(license.canonicalize().as_bytes());
+        let mut authenticode = signature_template("AuthenticodeSignature", algorithm)?;
+        complete_signature(&mut authenticode, &license_digest, key, algorithm)?;
+        let x509_data = {
+            let mut x509_data = authenticode.create_element(DSIG, "X509Data");
+            for certificate in key.certificates() {
+                let mut element = authenticode.create_element(DSIG, "X509Certificate");
+                element.set_text(&base64::encode(&certificate.der));
+                x509_data.children.push(Node::Element(element));
+            }
+            x509_data
+        };
+        let key_info = child_mut(&mut authenticode, DSIG, "KeyInfo")?;
+        key_info.children.push(Node::Element(x509_data));
+        key_info.children.push(Node::Text("\n".to_string()));
+
+        let issuer = child_mut(license, LICENSE, "issuer")?;
+        issuer.children = vec![Node::Element(authenticode)];
+    }
+
+    // The strong name signature covers the whole manifest.
+    let mut signature = signature_template("StrongNameSignature", algorithm)?;
+    complete_signature(&mut signature, &manifest_digest, key, algorithm)?;
+    let key_info = child_mut(&mut signature, DSIG, "KeyInfo")?;
+    key_info.set_attribute("Id", "StrongNameKeyInfo");
+    key_info.children.push(Node::Element(rel_data));
+    key_info.children.push(Node::Text("\n".to_string()));
+    root.children.push(Node::Element(signature));
+    Ok(())
+}
+
+/// Signs a manifest, returning the signed document.
+pub fn sign(
+    contents: &[u8],
+    key: &SigningKey,
+    algorithm: DigestAlgorithm,
+) -> Result<String, Error> {
+    let mut root = Element::parse(contents)?;
+    sign_element(&mut root, key, algorithm)?;
+    Ok(root.to_document())
+}
+
+fn base64_text(element: &Element) -> Result<Vec<u8>, Error> {
+    let compact: String = element
+        .text()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    base64::decode(compact).map_err(|e| Error::InvalidSignature(format!("invalid base64: {}", e)))
+}
+
+/// Checks an enveloped signature over `signed` against `key`, returning the
+/// digest of the signed content.
+fn verify_signature(
+    signature: &Element,
+    signed: &Element,
+    key: &RsaPublicKey,
+) -> Result<Vec<u8>, Error> {
+    let signed_info = child(signature, DSIG, "SignedInfo")?;
+    if child(signed_info, DSIG, "CanonicalizationMethod")?.attribute("Algorithm")
+        != Some(EXCLUSIVE_C14N)
+    {
+        return Err(invalid("unsupported canonicalization method"));
+    }
+    let reference = child(signed_info, DSIG, "Reference")?;
+    if reference.attribute("URI") != Some("") {
+        return Err(invalid("signature does not cover the whole document"));
+    }
+
+    let digest_algorithm = child(reference, DSIG, "DigestMethod")?
+        .attribute("Algorithm")
+        .and_then(DigestAlgorithm::from_uri)
+        .ok_or_else(|| invalid("unsupported digest method"))?;
+    let digest = digest_algorithm.digest(signed.canonicalize_excluding(signature).as_bytes());
+    if digest != base64_text(child(reference, DSIG, "DigestValue")?)? {
+        return Err(invalid("digest does not match the signed content"));
+    }
+
+    let signature_algorithm = child(signed_info, DSIG, "SignatureMethod")?
+        .attribute("Algorithm")
+        .and_then(DigestAlgorithm::from_signature_uri)
+        .ok_or_else(|| invalid("unsupported signature method"))?;
+    let value = base64_text(child(signature, DSIG, "SignatureValue")?)?;
+    key.verify(
+        padding(signature_algorithm),
+        &signature_algorithm.digest(signed_info.canonicalize().as_bytes()),
+        &value,
+    )
+    .map_err(|_| invalid("signature value does not match"))?;
+    Ok(digest)
+}
+
+/// Checks both signatures of a signed manifest, and that the Authenticode
+/// license names the manifest.
+///
+/// Returns the publisher's certificate. Whether the certificate is trusted
+/// is not checked.
+pub fn verify_element(root: &Element) -> Result<Certificate, Error> {
+    let signature = root
+        .child(DSIG, "Signature")
+        .ok_or_else(|| invalid("manifest is not signed"))?;
+    let key_info = child(signature, DSIG, "KeyInfo")?;
+    let rsa_key_value = child(child(key_info, DSIG, "KeyValue")?, DSIG, "RSAKeyValue")?;
+    let strong_name_key = RsaPublicKey::new(
+        BigUint::from_bytes_be(&base64_text(child(rsa_key_value, DSIG, "Modulus")?)?),
+        BigUint::from_bytes_be(&base64_text(child(rsa_key_value, DSIG, "Exponent")?)?),
+    )
+    .map_err(|e| Error::InvalidSignature(e.to_string()))?;
+    let manifest_digest = verify_signature(signature, root, &strong_name_key)?;
+
+    let license = child(child(key_info, REL_DATA, "RelData")?, LICENSE, "license")?;
+    let grant = child(license, LICENSE, "grant")?;
+    let information = child(grant, AUTHENTICODE, "ManifestInformation")?;
+    if !information
+        .attribute("Hash")
+        .unwrap_or("")
+        .eq_ignore_ascii_case(&reversed_hex(&manifest_digest))
+    {
+        return Err(invalid("license does not name this manifest"));
+    }
+
+    let authenticode = child(child(license, LICENSE, "issuer")?, DSIG, "Signature")?;
+    let subject = child(
+        child(grant, AUTHENTICODE, "AuthenticodePublisher")?,
+        AUTHENTICODE,
+        "X509SubjectName",
+    )?
+    .text();
+    let certificates = child(child(authenticode, DSIG, "KeyInfo")?, DSIG, "X509Data")?
+        .elements()
+        .filter(|e| e.is(DSIG, "X509Certificate"))
+        .map(|e| Certificate::from_der(&base64_text(e)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    let certificate = certificates
+        .iter()
+        .find(|c| crate::certificate::names_match(&c.subject, subject.trim()))
+        .or_else(|| certificates.first())
+        .ok_or_else(|| invalid("no certificate in Authenticode signature"))?;
+    let publisher_key = RsaPublicKey::from_pkcs1_der(&certificate.public_key)
+        .map_err(|e| Error::InvalidSignature(e.to_string()))?;
+    let _ = verify_signature(authenticode, license, &publisher_key)?;
+
+    Ok(certificate.clone())
+}
+
+/// Checks the signatures of a signed manifest. See [`verify_element`].
+pub fn verify(contents: &[u8]) -> Result<Certificate, Error> {
+    verify_element(&Element::parse(contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::applicationmanifest::ApplicationManifest;
+    use crate::deploymentmanifest::DeploymentManifest;
+    use crate::FromXML;
+
+    fn key() -> SigningKey {
+        SigningKey::from_pem(include_str!("example/signing-key.pem")).expect("Failure loading key")
+    }
+
+    #[test]
+    fn loads_keys() {
+        let pem = key();
+        assert_eq!(
+            pem.certificate().subject,
+            "CN=Example Corp, O=Example Corp, L=Renton, S=Washington, C=US"
+        );
+        assert_eq!(pem.certificates().len(), 2);
+        assert_eq!(
+            pem.issuer_key_hash().unwrap(),
+            "eb352f8f1933b4e8b86e8b056b79b1812156881f"
+        );
+
+        let pkcs12 =
+            SigningKey::from_pkcs12(include_bytes!("example/signing-key.pfx"), "example").unwrap();
+        assert_eq!(
+            pkcs12.certificate().thumbprint,
+            pem.certificate().thumbprint
+        );
+        assert_eq!(
+            pkcs12.public_key_token().unwrap(),
+            pem.public_key_token().unwrap()
+        );
+        assert!(
+            SigningKey::from_pkcs12(include_bytes!("example/signing-key.pfx"), "wrong").is_err()
+        );
+    }
+
+    #[test]
+    fn signs_deployment_manifests() {
+        let key = key();
+        let signed = sign(
+            include_bytes!("example/sample.application"),
+            &key,
+            DigestAlgorithm::Sha256,
+        )
+        .unwrap();
+
+        let certificate = verify(signed.as_bytes()).unwrap();
+        assert_eq!(certificate.thumbprint, key.certificate().thumbprint);
+
+        let manifest = DeploymentManifest::from_xml_bytes(signed.as_bytes()).unwrap();
+        let assembly = &manifest.assembly;
+        assert_eq!(
+            assembly.assembly_identity.public_key_token,
+            key.public_key_token().unwrap()
+        );
+        let signature = assembly.signature.as_ref().unwrap();
+        let verified = signature
+            .verify_publisher(assembly.publisher_identity.as_ref().unwrap())
+            .unwrap();
+        assert_eq!(verified.thumbprint, key.certificate().thumbprint);
+        assert_eq!(signature.certificates().unwrap().len(), 2);
+
+        let tampered = signed.replacen("version=\"1.0.0.0\"", "version=\"1.0.0.1\"", 1);
+        assert!(matches!(
+            verify(tampered.as_bytes()),
+            Err(Error::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn signs_application_manifests() {
+        let key = key();
+        let signed = sign(
+            include_bytes!("example/sample.exe.manifest"),
+            &key,
+            DigestAlgorithm::Sha1,
+        )
+        .unwrap();
+        let _ = verify(signed.as_bytes()).unwrap();
+
+        // Signing again replaces the previous signature.
+        let resigned = sign(signed.as_bytes(), &key, DigestAlgorithm::Sha256).unwrap();
+        assert_eq!(resigned.matches("StrongNameSignature").count(), 1);
+        let _ = verify(resigned.as_bytes()).unwrap();
+
+        let manifest = ApplicationManifest::from_xml_bytes(resigned.as_bytes()).unwrap();
+        let identity = manifest.assembly.publisher_identity.unwrap();
+        assert_eq!(identity.name, key.certificate().subject);
+    }
+}
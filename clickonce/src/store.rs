@@ -0,0 +1,240 @@
+//! Folder names used by the ClickOnce application store
+//!
+//! Installed applications live under `%LOCALAPPDATA%\Apps\2.0`, two levels
+//! of randomly named folders down:
+//!
+//! ```text
+//! Apps\2.0\<random>\<random>\mtgo..tion_3d3d5fb5ad5ec1a8_0003.0004_7f3c9a0e1b2d4c5f
+//! Apps\2.0\<random>\<random>\mtgo.exe_3d3d5fb5ad5ec1a8_0003.0004_none_0a1b2c3d4e5f6a7b
+//! ```
+//!
+//! Each folder name is derived from an assembly identity: the name, shortened
+//! to its first and last four characters if it is long, the public key token,
+//! the major and minor version as four hex digits each, and for applications
+//! the culture. The trailing hash is computed by the store from the full
+//! identity with an undocumented algorithm, so it is treated as opaque and
+//! folders are matched on everything before it.
+use crate::version::Version;
+use crate::{applicationmanifest, deploymentmanifest, Error};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Names longer than this are shortened in store folder names
+const MAX_NAME_LENGTH: usize = 10;
+
+/// Number of characters kept from each end of a shortened name
+const SHORTENED_PART_LENGTH: usize = 4;
+
+/// Length of the hash at the end of store folder names
+const HASH_LENGTH: usize = 16;
+
+/// Public key token used in folder names of unsigned assemblies
+const NULL_PUBLIC_KEY_TOKEN: &str = "0000000000000000";
+
+/// What a store folder holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreKind {
+    /// A deployment, named after the deployment manifest's identity
+    Deployment,
+    /// An application's files, named after the application manifest's identity
+    Application,
+}
+
+/// The deterministic part of a store folder name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreName {
+    /// Whether the folder holds a deployment or an application
+    pub kind: StoreKind,
+    /// Name from the assembly identity
+    pub name: String,
+    /// Public key token from the assembly identity, if signed
+    pub public_key_token: Option<String>,
+    /// Version from the assembly identity
+    pub version: Version,
+    /// Language from the assembly identity, for applications
+    pub language: Option<String>,
+}
+
+impl StoreName {
+    /// The store name of a deployment manifest's identity.
+    pub fn from_deployment(identity: &deploymentmanifest::AssemblyIdentity) -> Result<Self, Error> {
+        Ok(Self {
+            kind: StoreKind::Deployment,
+            name: identity.name.clone(),
+            public_key_token: Some(identity.public_key_token.clone()),
+            version: identity.version.parse()?,
+            language: None,
+        })
+    }
+
+    /// The store name of an application manifest's identity.
+    pub fn from_application(
+        identity: &applicationmanifest::AssemblyIdentity,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            kind: StoreKind::Application,
+            name: identity.name.clone(),
+            public_key_token: identity.public_key_token.clone(),
+            version: identity.version.parse()?,
+            language: identity.language.clone(),
+        })
+    }
+
+    /// The folder name up to, and including, the underscore before the hash.
+    pub fn prefix(&self) -> String {
+        let token = match self.public_key_token.as_deref() {
+            Some(token) if !token.is_empty() => token,
+            _ => NULL_PUBLIC_KEY_TOKEN,
+        };
+        let mut prefix = format!(
+            "{}_{}_{:04x}.{:04x}_",
+            shorten(&self.name),
+            token,
+            self.version.major,
+            self.version.minor
+        );
+        if self.kind == StoreKind::Application {
+            let culture = match self.language.as_deref() {
+                None | Some("") | Some("*") => "none",
+                Some(language) if language.eq_ignore_ascii_case("neutral") => "none",
+                Some(language) => language,
+            };
+            prefix.push_str(culture);
+            prefix.push('_');
+        }
+        prefix.to_lowercase()
+    }
+
+    /// Whether `folder_name` is a store folder for this identity.
+    ///
+    /// The store only records the major and minor version in folder names,
+    /// so installs of versions differing only in build or revision match.
+    pub fn matches(&self, folder_name: &str) -> bool {
+        let prefix = self.prefix();
+        let folder_name = folder_name.to_lowercase();
+        match folder_name.strip_prefix(&prefix) {
+            Some(hash) => hash.len() == HASH_LENGTH && hash.bytes().all(|b| b.is_ascii_hexdigit()),
+            None => false,
+        }
+    }
+
+    /// Finds the folders for this identity in a copy of the `Apps\2.0`
+    /// directory.
+    pub fn locate(&self, apps: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut found = Vec::new();
+        for folder in store_folders(apps)? {
+            let matches = folder
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| self.matches(name));
+            if matches {
+                found.push(folder);
+            }
+        }
+        found.sort();
+        Ok(found)
+    }
+}
+
+/// Lists every folder two levels of random folders below `apps`, skipping
+/// the `Data` folder that holds application data rather than installs.
+pub fn store_folders(apps: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut folders = Vec::new();
+    for first in subdirectories(apps)? {
+        if first
+            .file_name()
+            .is_some_and(|name| name.eq_ignore_ascii_case("Data"))
+        {
+            continue;
+        }
+        for second in subdirectories(&first)? {
+            for folder in subdirectories(&second)? {
+                if folder.file_name().is_some_and(|name| name != "manifests") {
+                    folders.push(folder);
+                }
+            }
+        }
+    }
+    Ok(folders)
+}
+
+fn subdirectories(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut directories = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            directories.push(entry.path());
+        }
+    }
+    Ok(directories)
+}
+
+/// Shortens a name as the store does, keeping its first and last characters.
+fn shorten(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    if chars.len() <= MAX_NAME_LENGTH {
+        return name.to_string();
+    }
+    let start: String = chars[..SHORTENED_PART_LENGTH].iter().collect();
+    let end: String = chars[chars.len() - SHORTENED_PART_LENGTH..]
+        .iter()
+        .collect();
+    format!("{}..{}", start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::applicationmanifest::ApplicationManifest;
+    use crate::deploymentmanifest::DeploymentManifest;
+    use crate::FromXML;
+
+    fn deployment() -> StoreName {
+        let manifest =
+            DeploymentManifest::from_xml_bytes(include_bytes!("example/sample.application"))
+                .unwrap();
+        StoreName::from_deployment(&manifest.assembly.assembly_identity).unwrap()
+    }
+
+    #[test]
+    fn derives_folder_names() {
+        assert_eq!(
+            deployment().prefix(),
+            "my a...app_43cb1e8e7a352766_0001.0000_"
+        );
+
+        let manifest =
+            ApplicationManifest::from_xml_bytes(include_bytes!("example/sample.exe.manifest"))
+                .unwrap();
+        let application =
+            StoreName::from_application(&manifest.assembly.assembly_identity).unwrap();
+        assert!(application.prefix().ends_with("_none_"));
+
+        let name = StoreName {
+            kind: StoreKind::Application,
+            name: "MTGO.exe".to_string(),
+            public_key_token: None,
+            version: Version::new(3, 4, 180, 1),
+            language: Some("neutral".to_string()),
+        };
+        assert_eq!(name.prefix(), "mtgo.exe_0000000000000000_0003.0004_none_");
+    }
+
+    #[test]
+    fn locates_store_folders() {
+        let apps = tempfile::tempdir().unwrap();
+        let store = apps.path().join("ABCDEFGH.IJK").join("LMNOPQRS.TUV");
+        let folder = store.join("my a...app_43cb1e8e7a352766_0001.0000_0123456789abcdef");
+        fs::create_dir_all(&folder).unwrap();
+        fs::create_dir_all(store.join("manifests")).unwrap();
+        fs::create_dir_all(store.join("my a...app_43cb1e8e7a352766_0002.0000_0123456789abcdef"))
+            .unwrap();
+        fs::create_dir_all(apps.path().join("Data").join("A").join("B").join("C")).unwrap();
+
+        let name = deployment();
+        assert!(!name.matches("my a...app_43cb1e8e7a352766_0001.0000_"));
+        assert_eq!(name.locate(apps.path()).unwrap(), vec![folder]);
+        assert_eq!(store_folders(apps.path()).unwrap().len(), 2);
+    }
+}
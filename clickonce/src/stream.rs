@@ -0,0 +1,191 @@
+//! Streaming access to the contents of large application manifests
+//!
+//! Application manifests for large clients list thousands of files and
+//! dependencies. [`ManifestItems`] pulls `<file>` and `<dependency>`
+//! elements out of a reader one at a time, so only a single item is held in
+//! memory at once. Use [`FromXML`](crate::FromXML) when the whole document
+//! is needed instead.
+use crate::applicationmanifest::{Dependency, File};
+use crate::limits::{syntax_error, LimitedReader};
+use crate::{Error, ParseLimits};
+use std::io::Read;
+use xml::reader::{EventReader, XmlEvent};
+use xml::writer::EmitterConfig;
+
+/// An item yielded while streaming an application manifest
+#[derive(Debug)]
+pub enum ManifestItem {
+    /// A `<file>` element
+    File(File),
+    /// A `<dependency>` element
+    Dependency(Dependency),
+}
+
+/// Iterator over the `<file>` and `<dependency>` children of an
+/// application manifest's top-level `<assembly>` element.
+pub struct ManifestItems<R: Read> {
+    reader: EventReader<LimitedReader<R>>,
+    limits: ParseLimits,
+    depth: usize,
+    finished: bool,
+}
+
+impl<R: Read> ManifestItems<R> {
+    /// Creates an iterator that reads the manifest from `reader`, applying
+    /// the default [`ParseLimits`].
+    pub fn new(reader: R) -> Self {
+        Self::with_limits(reader, ParseLimits::default())
+    }
+
+    /// Creates an iterator that reads the manifest from `reader`, failing if
+    /// the document breaks any of `limits`.
+    pub fn with_limits(reader: R, limits: ParseLimits) -> Self {
+        let reader = LimitedReader::new(reader, limits.max_document_size);
+        Self {
+            reader: limits.event_reader(reader),
+            limits,
+            depth: 0,
+            finished: false,
+        }
+    }
+
+    /// Reads the next event, checking it against the limits.
+    fn next_event(&mut self) -> Result<XmlEvent, Error> {
+        let event = match self.reader.next() {
+            Ok(event) => event,
+            Err(e) => {
+                return Err(if self.reader.source().exceeded() {
+                    Error::DocumentTooLarge(self.limits.max_document_size)
+                } else {
+                    self.limits
+                        .doctype_error(&self.reader)
+                        .unwrap_or_else(|| syntax_error(e))
+                })
+            }
+        };
+        match event {
+            XmlEvent::StartElement { .. } => self.depth += 1,
+            XmlEvent::EndElement { .. } => self.depth -= 1,
+            _ => {}
+        }
+        self.limits.check_event(&self.reader, &event, self.depth)?;
+        Ok(event)
+    }
+
+    /// Copies the element whose start event has just been read into a
+    /// standalone document, and deserializes it.
+    fn read_item(&mut self, start: XmlEvent) -> Result<ManifestItem, Error> {
+        let is_file =
+            matches!(&start, XmlEvent::StartElement { name, .. } if name.local_name == "file");
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = EmitterConfig::new()
+                .write_document_declaration(false)
+                .create_writer(&mut buffer);
+            // The item's start element has already been counted
+            let depth = self.depth - 1;
+            let mut event = start;
+            loop {
+                if let Some(writer_event) = event.as_writer_event() {
+                    writer.write(writer_event).map_err(xml_error)?;
+                }
+                if self.depth == depth {
+                    break;
+                }
+                event = self.next_event()?;
+            }
+        }
+
+        if is_file {
+            Ok(ManifestItem::File(serde_xml_rs::from_reader(&buffer[..])?))
+        } else {
+            Ok(ManifestItem::Dependency(serde_xml_rs::from_reader(
+                &buffer[..],
+            )?))
+        }
+    }
+}
+
+impl<R: Read> Iterator for ManifestItems<R> {
+    type Item = Result<ManifestItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let event = match self.next_event() {
+                Ok(event) => event,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            };
+
+            match event {
+                XmlEvent::StartElement { ref name, .. }
+                    if self.depth == 2
+                        && (name.local_name == "file" || name.local_name == "dependency") =>
+                {
+                    let item = self.read_item(event);
+                    if item.is_err() {
+                        self.finished = true;
+                    }
+                    return Some(item);
+                }
+                XmlEvent::EndDocument => self.finished = true,
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+fn xml_error(e: xml::writer::Error) -> Error {
+    Error::XML(serde_xml_rs::Error::Custom {
+        field: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams_files_and_dependencies() {
+        let manifest = include_bytes!("example/sample.exe.manifest");
+        let items = ManifestItems::new(&manifest[..])
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Failure streaming manifest");
+
+        let files: Vec<&File> = items
+            .iter()
+            .filter_map(|item| match item {
+                ManifestItem::File(file) => Some(file),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(items.len(), 6);
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].name, "MyApplication.exe.config");
+        assert!(files[0].hash.is_some());
+    }
+
+    #[test]
+    fn reports_malformed_documents() {
+        let manifest = b"<assembly><file name=\"a\" size=\"1\"></assembly>";
+        let mut items = ManifestItems::new(&manifest[..]);
+        assert!(matches!(items.next(), Some(Err(_))));
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn applies_limits() {
+        let manifest = include_bytes!("example/sample.exe.manifest");
+        let limits = ParseLimits {
+            max_document_size: 1024,
+            ..ParseLimits::default()
+        };
+        let result =
+            ManifestItems::with_limits(&manifest[..], limits).collect::<Result<Vec<_>, _>>();
+        assert!(matches!(result, Err(Error::DocumentTooLarge(1024))));
+    }
+}
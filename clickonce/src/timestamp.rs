@@ -0,0 +1,290 @@
+//! Authenticode timestamp countersignatures
+//!
+//! The Authenticode signature of a ClickOnce manifest may carry an
+//! `<as:Timestamp>` in its `<Object>` element: a base64 encoded PKCS #7
+//! `SignedData` issued by a timestamp authority. Older authorities sign a
+//! `signingTime` attribute; RFC 3161 authorities sign a `TSTInfo` structure
+//! containing the time. Both are decoded here.
+//!
+//! Neither the authority's signature over the timestamp nor the
+//! `messageImprint` binding it to the manifest signature is verified, so the
+//! decoded time is only what the timestamp claims. Anyone able to edit the
+//! manifest can substitute a timestamp claiming any time.
+use crate::certificate::{format_name, serial_hex, Certificate};
+use crate::Error;
+use std::convert::TryFrom;
+use time::OffsetDateTime;
+use x509_parser::der_parser::asn1_rs::{
+    ASN1TimeZone, Any, Class, FromDer, GeneralizedTime, Tag, UtcTime,
+};
+use x509_parser::prelude::X509Name;
+
+/// 1.2.840.113549.1.7.2
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+/// 1.2.840.113549.1.9.5
+const OID_SIGNING_TIME: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x05];
+/// 1.2.840.113549.1.9.16.1.4
+const OID_TST_INFO: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04,
+];
+
+/// The kind of timestamp authority that issued a timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampKind {
+    /// A legacy Authenticode timestamp, carrying a `signingTime` attribute
+    Authenticode,
+    /// An RFC 3161 timestamp token, carrying a `TSTInfo` structure
+    Rfc3161,
+}
+
+/// A decoded timestamp countersignature
+#[derive(Debug, Clone)]
+pub struct Timestamp {
+    /// The kind of timestamp
+    pub kind: TimestampKind,
+    /// The time the timestamp claims the signature existed. This is not
+    /// verified against the authority's signature.
+    pub claimed_time: OffsetDateTime,
+    /// Subject of the certificate the timestamp names as its signer, if it
+    /// is included. This is not verified either.
+    pub authority: Option<String>,
+    /// Certificates included in the timestamp
+    pub certificates: Vec<Certificate>,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Timestamp(message.to_string())
+}
+
+/// Reads one DER encoded value, returning it and the remaining input.
+fn read(input: &[u8]) -> Result<(Any<'_>, &[u8]), Error> {
+    let (rest, value) =
+        Any::from_der(input).map_err(|e| Error::Timestamp(format!("malformed DER: {}", e)))?;
+    Ok((value, rest))
+}
+
+/// Reads one DER value, checking it has the expected class and tag, and
+/// returns its contents and the remaining input.
+fn expect(input: &[u8], class: Class, tag: Tag) -> Result<(&[u8], &[u8]), Error> {
+    let (value, rest) = read(input)?;
+    if value.class() != class || value.tag() != tag {
+        return Err(invalid("unexpected tag"));
+    }
+    Ok((value.data, rest))
+}
+
+/// Splits the contents of a constructed value into its elements, each with
+/// its encoding.
+fn elements(mut input: &[u8]) -> Result<Vec<(Any<'_>, &[u8])>, Error> {
+    let mut elements = Vec::new();
+    while !input.is_empty() {
+        let (value, rest) = read(input)?;
+        elements.push((value, &input[..input.len() - rest.len()]));
+        input = rest;
+    }
+    Ok(elements)
+}
+
+fn is_universal(value: &Any<'_>, tag: Tag) -> bool {
+    value.class() == Class::Universal && value.tag() == tag
+}
+
+fn is_context(value: &Any<'_>, tag: u32) -> bool {
+    value.class() == Class::ContextSpecific && value.tag() == Tag(tag)
+}
+
+/// Parses a UTCTime or GeneralizedTime value, which DER requires to be in
+/// UTC and end with `Z`.
+fn parse_time(value: &Any<'_>) -> Result<OffsetDateTime, Error> {
+    let invalid_time = |_| invalid("invalid time");
+    let (time, zone) = match value.tag() {
+        Tag::UtcTime => {
+            let time = UtcTime::try_from(value).map_err(invalid_time)?;
+            (time.utc_adjusted_datetime(), time.0.tz)
+        }
+        Tag::GeneralizedTime => {
+            let time = GeneralizedTime::try_from(value).map_err(invalid_time)?;
+            (time.utc_datetime(), time.0.tz)
+        }
+        _ => return Err(invalid("invalid time")),
+    };
+    if zone != ASN1TimeZone::Z {
+        return Err(invalid("time is not in UTC"));
+    }
+    time.map_err(invalid_time)
+}
+
+/// Extracts the issuer and serial number from a SignerInfo's
+/// IssuerAndSerialNumber.
+fn signer_identifier(signer_info: &[(Any<'_>, &[u8])]) -> Option<(String, String)> {
+    let (sid, _) = signer_info.get(1)?;
+    if !is_universal(sid, Tag::Sequence) {
+        return None;
+    }
+    let fields = elements(sid.data).ok()?;
+    let (issuer, serial) = match fields.as_slice() {
+        [(_, issuer), (serial, _), ..] if is_universal(serial, Tag::Integer) => (issuer, serial),
+        _ => return None,
+    };
+    let (_, issuer) = X509Name::from_der(issuer).ok()?;
+    Some((format_name(&issuer), serial_hex(serial.data)))
+}
+
+/// Finds the signingTime attribute in a SignerInfo's signed attributes.
+fn signing_time(signer_info: &[(Any<'_>, &[u8])]) -> Result<Option<OffsetDateTime>, Error> {
+    let attributes = match signer_info.iter().find(|(value, _)| is_context(value, 0)) {
+        Some((attributes, _)) => attributes,
+        None => return Ok(None),
+    };
+    for (attribute, _) in elements(attributes.data)? {
+        let (oid, values) = expect(attribute.data, Class::Universal, Tag::Oid)?;
+        if oid == OID_SIGNING_TIME {
+            let (values, _) = expect(values, Class::Universal, Tag::Set)?;
+            let (time, _) = read(values)?;
+            return parse_time(&time).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Extracts genTime from a DER encoded TSTInfo.
+fn tst_info_time(tst_info: &[u8]) -> Result<OffsetDateTime, Error> {
+    let (tst_info, _) = expect(tst_info, Class::Universal, Tag::Sequence)?;
+    // version, policy, messageImprint, serialNumber, genTime, ...
+    let fields = elements(tst_info)?;
+    match fields.get(4) {
+        Some((time, _)) if is_universal(time, Tag::GeneralizedTime) => parse_time(time),
+        _ => Err(invalid("missing genTime")),
+    }
+}
+
+impl Timestamp {
+    /// Decodes a DER encoded PKCS #7 timestamp.
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        // ContentInfo ::= SEQUENCE { contentType, [0] EXPLICIT content }
+        let (content_info, _) = expect(der, Class::Universal, Tag::Sequence)?;
+        let (content_type, rest) = expect(content_info, Class::Universal, Tag::Oid)?;
+        if content_type != OID_SIGNED_DATA {
+            return Err(invalid("not a SignedData structure"));
+        }
+        let (content, _) = expect(rest, Class::ContextSpecific, Tag(0))?;
+        let (signed_data, _) = expect(content, Class::Universal, Tag::Sequence)?;
+
+        // SignedData ::= SEQUENCE { version, digestAlgorithms,
+        //   encapContentInfo, [0] certificates OPTIONAL,
+        //   [1] crls OPTIONAL, signerInfos }
+        let fields = elements(signed_data)?;
+        if fields.len() < 4 || !is_universal(&fields[0].0, Tag::Integer) {
+            return Err(invalid("malformed SignedData"));
+        }
+
+        let (encapsulated_type, encapsulated) =
+            expect(fields[2].0.data, Class::Universal, Tag::Oid)?;
+
+        let mut certificates = Vec::new();
+        for (value, _) in &fields[3..] {
+            if is_context(value, 0) {
+                for (_, certificate) in elements(value.data)? {
+                    certificates.push(Certificate::from_der(certificate)?);
+                }
+            }
+        }
+
+        let signer_infos = fields
+            .iter()
+            .rev()
+            .find(|(value, _)| is_universal(value, Tag::Set))
+            .map(|(value, _)| value.data)
+            .ok_or_else(|| invalid("missing signerInfos"))?;
+        let (signer_info, _) = expect(signer_infos, Class::Universal, Tag::Sequence)?;
+        let signer_info = elements(signer_info)?;
+
+        let authority = signer_identifier(&signer_info).and_then(|(issuer, serial)| {
+            certificates
+                .iter()
+                .find(|c| c.issuer == issuer && c.serial_number == serial)
+                .map(|c| c.subject.clone())
+        });
+
+        let (kind, claimed_time) = if encapsulated_type == OID_TST_INFO {
+            let (explicit, _) = expect(encapsulated, Class::ContextSpecific, Tag(0))?;
+            let (tst_info, _) = expect(explicit, Class::Universal, Tag::OctetString)?;
+            (TimestampKind::Rfc3161, tst_info_time(tst_info)?)
+        } else {
+            let time = signing_time(&signer_info)?.ok_or_else(|| invalid("missing signingTime"))?;
+            (TimestampKind::Authenticode, time)
+        };
+
+        Ok(Self {
+            kind,
+            claimed_time,
+            authority,
+            certificates,
+        })
+    }
+
+    /// Whether the claimed time falls within the certificate's validity
+    /// window. As the timestamp is not verified, this is not proof that the
+    /// build was signed while the certificate was valid.
+    pub fn claimed_within_validity(&self, certificate: &Certificate) -> bool {
+        certificate.is_valid_at(self.claimed_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deploymentmanifest::DeploymentManifest;
+    use crate::FromXML;
+
+    #[test]
+    fn decodes_rfc3161_timestamp_from_manifest() {
+        let manifest =
+            DeploymentManifest::from_xml_bytes(include_bytes!("example/sample.application"))
+                .expect("Failure parsing manifest");
+        let signature = manifest.assembly.signature.unwrap();
+        let timestamp = signature.timestamp().unwrap().expect("No timestamp");
+
+        assert_eq!(timestamp.kind, TimestampKind::Rfc3161);
+        assert_eq!(
+            timestamp.authority.as_deref(),
+            Some("CN=Example Timestamp Authority, O=Example Corp, C=US")
+        );
+        assert_eq!(timestamp.claimed_time.year(), 2026);
+        assert_eq!(timestamp.certificates.len(), 2);
+
+        let certificate = signature.publisher_certificate().unwrap().unwrap();
+        assert!(timestamp.claimed_within_validity(&certificate));
+    }
+
+    #[test]
+    fn decodes_authenticode_timestamp() {
+        let timestamp = Timestamp::from_der(include_bytes!("example/authenticode-timestamp.der"))
+            .expect("Failure decoding timestamp");
+        assert_eq!(timestamp.kind, TimestampKind::Authenticode);
+        assert_eq!(
+            timestamp.authority.as_deref(),
+            Some("CN=Example Timestamp Authority, O=Example Corp, C=US")
+        );
+        assert_eq!(timestamp.claimed_time.year(), 2026);
+    }
+
+    #[test]
+    fn parses_times() {
+        let time = |der: &[u8]| parse_time(&read(der).unwrap().0);
+        assert_eq!(
+            time(b"\x17\x0d220101120000Z").unwrap().unix_timestamp(),
+            1_641_038_400
+        );
+        assert_eq!(
+            time(b"\x18\x1120220101120000.5Z").unwrap().unix_timestamp(),
+            1_641_038_400
+        );
+        // Only a fraction of a second may come between the seconds and Z
+        assert!(time(b"\x18\x1120220101120000x5Z").is_err());
+        assert!(time(b"\x18\x0e20220101120000").is_err());
+        assert!(time(b"\x18\x1320220101120000+0100").is_err());
+        assert!(time(b"\x02\x01\x01").is_err());
+        assert!(Timestamp::from_der(b"\x30\x03\x02\x01\x01").is_err());
+    }
+}
@@ -0,0 +1,394 @@
+//! Regenerating manifest sizes and hashes from build output, as
+//! `mage -update` does
+//!
+//! After a build, every `<file>` and installed `<dependentAssembly>` in the
+//! application manifest needs the size and digest of the file that will be
+//! deployed, and the deployment manifest needs those of the application
+//! manifest. Updating a manifest invalidates its signature, so the
+//! signature is removed and the manifest must be signed again with
+//! [`crate::signing`].
+use crate::digest::DigestAlgorithm;
+use crate::dom::{Element, Node};
+use crate::Error;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ASM_V1: &str = "urn:schemas-microsoft-com:asm.v1";
+const ASM_V2: &str = "urn:schemas-microsoft-com:asm.v2";
+const DSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+const IDENTITY_TRANSFORM: &str = "urn:schemas-microsoft-com:HashTransforms.Identity";
+
+/// Changes made while updating an application manifest
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Changes {
+    /// Files in the directory that were not in the manifest
+    pub added: Vec<String>,
+    /// Files and dependencies in the manifest that are not in the directory
+    pub removed: Vec<String>,
+    /// Files and dependencies whose size or digest changed
+    pub updated: Vec<String>,
+}
+
+/// Lists the files under `directory`, keyed by their lowercase manifest
+/// name. Names use `\` separators, and a `.deploy` extension is dropped as
+/// ClickOnce servers add it when `mapFileExtensions` is set. Symbolic links
+/// to directories are not followed, so a link loop cannot recurse forever.
+fn scan(
+    directory: &Path,
+    prefix: &str,
+    files: &mut BTreeMap<String, (String, PathBuf)>,
+) -> Result<(), Error> {
+    let mut entries = fs::read_dir(directory)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().into_string().map_err(|name| {
+            Error::Encoding(format!("file name {:?} is not valid Unicode", name))
+        })?;
+        let path = entry.path();
+        if entry.file_type()?.is_symlink() && path.is_dir() {
+            continue;
+        } else if path.is_dir() {
+            scan(&path, &format!("{}{}\\", prefix, name), files)?;
+        } else if path.is_file() {
+            let name = format!("{}{}", prefix, name);
+            let name = strip_deploy_extension(&name).to_string();
+            let _ = files.insert(name.to_lowercase(), (name, path));
+        }
+    }
+    Ok(())
+}
+
+/// Drops a `.deploy` extension, in any case, from a file name.
+fn strip_deploy_extension(name: &str) -> &str {
+    const DEPLOY: &str = ".deploy";
+    match name.len().checked_sub(DEPLOY.len()) {
+        Some(end) if name.is_char_boundary(end) && name[end..].eq_ignore_ascii_case(DEPLOY) => {
+            &name[..end]
+        }
+        _ => name,
+    }
+}
+
+/// Sets the digest in an element's `<hash>`, adding one if it is missing.
+fn set_hash(scope: &Element, element: &mut Element, algorithm: DigestAlgorithm, digest: &[u8]) {
+    let value = base64::encode(digest);
+    if let Some(hash) = element.children.iter_mut().find_map(|node| match node {
+        Node::Element(e) if e.name.local_name == "hash" => Some(e),
+        _ => None,
+    }) {
+        if hash.child(DSIG, "DigestMethod").is_some() && hash.child(DSIG, "DigestValue").is_some() {
+            if let Some(method) = hash.child_mut(DSIG, "DigestMethod") {
+                method.set_attribute("Algorithm", algorithm.manifest_uri());
+            }
+            if let Some(digest_value) = hash.child_mut(DSIG, "DigestValue") {
+                digest_value.set_text(&value);
+            }
+            return;
+        }
+    }
+    element.children.retain(|node| match node {
+        Node::Element(e) => e.name.local_name != "hash",
+        _ => true,
+    });
+
+    let namespace = element.name.namespace.clone().unwrap_or_default();
+    let mut hash = scope.create_element(&namespace, "hash");
+    let mut transforms = scope.create_element(DSIG, "Transforms");
+    let mut transform = scope.create_element(DSIG, "Transform");
+    transform.set_attribute("Algorithm", IDENTITY_TRANSFORM);
+    transforms.children.push(Node::Element(transform));
+    let mut method = scope.create_element(DSIG, "DigestMethod");
+    method.set_attribute("Algorithm", algorithm.manifest_uri());
+    let mut digest_value = scope.create_element(DSIG, "DigestValue");
+    digest_value.set_text(&value);
+    hash.children = vec![
+        Node::Element(transforms),
+        Node::Element(method),
+        Node::Element(digest_value),
+    ];
+    element.children.push(Node::Element(hash));
+}
+
+/// The digest currently recorded in an element's `<hash>`, if any.
+fn current_digest(element: &Element) -> Option<String> {
+    element
+        .elements()
+        .find(|e| e.name.local_name == "hash")
+        .and_then(|hash| hash.child(DSIG, "DigestValue"))
+        .map(|value| value.text().trim().to_string())
+}
+
+/// Sets an element's `size` and hash from a file, returning whether either
+/// changed.
+fn update_entry(
+    scope: &Element,
+    element: &mut Element,
+    path: &Path,
+    algorithm: DigestAlgorithm,
+) -> Result<bool, Error> {
+    let (size, digest) = algorithm.digest_reader(fs::File::open(path)?)?;
+    let size = size.to_string();
+    let changed = element.attribute("size") != Some(size.as_str())
+        || current_digest(element) != Some(base64::encode(&digest));
+    element.set_attribute("size", &size);
+    set_hash(scope, element, algorithm, &digest);
+    Ok(changed)
+}
+
+fn is_whitespace(node: Option<&Node>) -> bool {
+    matches!(node, Some(Node::Text(text)) if text.trim().is_empty())
+}
+
+/// Regenerates the sizes and hashes of an application manifest's files and
+/// installed dependencies from the build output in `directory`.
+///
+/// Files and dependencies missing from the directory are dropped, and files
+/// in the directory that the manifest does not list are added as `<file>`
+/// elements. The manifest itself, and any deployment manifests, are not
+/// listed.
+pub fn update_application_manifest(
+    root: &mut Element,
+    directory: &Path,
+    algorithm: DigestAlgorithm,
+) -> Result<Changes, Error> {
+    let mut found = BTreeMap::new();
+    scan(directory, "", &mut found)?;
+    if let Some(name) = root
+        .child(ASM_V1, "assemblyIdentity")
+        .and_then(|identity| identity.attribute("name"))
+    {
+        let _ = found.remove(&format!("{}.manifest", name).to_lowercase());
+    }
+    found.retain(|name, _| !name.ends_with(".application"));
+
+    root.remove_children(DSIG, "Signature");
+    let mut changes = Changes::default();
+    let mut children = Vec::new();
+    for mut node in std::mem::take(&mut root.children) {
+        let entry = match &mut node {
+            Node::Element(e) if e.name.local_name == "file" => Some(e),
+            Node::Element(e) if e.name.local_name == "dependency" => {
+                e.children.iter_mut().find_map(|node| match node {
+                    Node::Element(a)
+                        if a.name.local_name == "dependentAssembly"
+                            && a.attribute("codebase").is_some() =>
+                    {
+                        Some(a)
+                    }
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+
+        if let Some(entry) = entry {
+            let name = entry
+                .attribute("name")
+                .or_else(|| entry.attribute("codebase"))
+                .unwrap_or_default()
+                .to_string();
+            match found.remove(&name.to_lowercase()) {
+                Some((_, path)) => {
+                    if update_entry(root, entry, &path, algorithm)? {
+                        changes.updated.push(name);
+                    }
+                }
+                None => {
+                    // Drop the indentation along with the element.
+                    if is_whitespace(children.last()) {
+                        let _ = children.pop();
+                    }
+                    changes.removed.push(name);
+                    continue;
+                }
+            }
+        }
+        children.push(node);
+    }
+
+    // New files go after the last file or dependency, indented to match.
+    let anchor = children
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(index, node)| match node {
+            Node::Element(e) if matches!(e.name.local_name.as_str(), "file" | "dependency") => {
+                Some((index, e.name.namespace.clone().unwrap_or_default()))
+            }
+            _ => None,
+        });
+    let (mut position, indent, namespace) = match anchor {
+        Some((index, namespace)) => (
+            index + 1,
+            children[..index]
+                .last()
+                .filter(|n| is_whitespace(Some(n)))
+                .cloned(),
+            namespace,
+        ),
+        None => (children.len(), None, ASM_V2.to_string()),
+    };
+    for (_, (name, path)) in found {
+        let mut file = root.create_element(&namespace, "file");
+        file.set_attribute("name", &name);
+        let _ = update_entry(root, &mut file, &path, algorithm)?;
+        if let Some(indent) = &indent {
+            children.insert(position, indent.clone());
+            position += 1;
+        }
+        children.insert(position, Node::Element(file));
+        position += 1;
+        changes.added.push(name);
+    }
+
+    root.children = children;
+    Ok(changes)
+}
+
+/// Sets the size, hash and identity of the application manifest referenced
+/// by a deployment manifest. `application_manifest` must be the final,
+/// signed application manifest.
+pub fn update_deployment_manifest(
+    root: &mut Element,
+    application_manifest: &[u8],
+    algorithm: DigestAlgorithm,
+) -> Result<(), Error> {
+    let application = Element::parse(application_manifest)?;
+    let identity = application
+        .child(ASM_V1, "assemblyIdentity")
+        .ok_or_else(|| Error::MissingElement("assemblyIdentity".to_string()))?;
+
+    root.remove_children(DSIG, "Signature");
+    let mut children = std::mem::take(&mut root.children);
+    let assembly = children
+        .iter_mut()
+        .filter_map(|node| match node {
+            Node::Element(e) if e.name.local_name == "dependency" => Some(e),
+            _ => None,
+        })
+        .flat_map(|dependency| dependency.children.iter_mut())
+        .find_map(|node| match node {
+            Node::Element(e)
+                if e.name.local_name == "dependentAssembly"
+                    && e.attribute("codebase").is_some() =>
+            {
+                Some(e)
+            }
+            _ => None,
+        });
+
+    let result = match assembly {
+        Some(assembly) => {
+            assembly.set_attribute("size", &application_manifest.len().to_string());
+            set_hash(
+                root,
+                assembly,
+                algorithm,
+                &algorithm.digest(application_manifest),
+            );
+            if let Some(reference) = assembly.children.iter_mut().find_map(|node| match node {
+                Node::Element(e) if e.name.local_name == "assemblyIdentity" => Some(e),
+                _ => None,
+            }) {
+                for attribute in &identity.attributes {
+                    reference.set_attribute(&attribute.name.local_name, &attribute.value);
+                }
+            }
+            Ok(())
+        }
+        None => Err(Error::MissingElement("dependentAssembly".to_string())),
+    };
+    root.children = children;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::applicationmanifest::ApplicationManifest;
+    use crate::deploymentmanifest::DeploymentManifest;
+    use crate::FromXML;
+
+    fn write(root: &Path, name: &str, contents: &[u8]) {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn updates_manifests_from_directory() {
+        let directory = tempfile::tempdir().unwrap();
+        write(directory.path(), "MyApplication.exe.deploy", b"executable");
+        write(
+            directory.path(),
+            "MyApplication.exe.config.DEPLOY",
+            b"<configuration />",
+        );
+        write(
+            directory.path(),
+            "CardDataSource/client_DMU.xml",
+            b"<cards />",
+        );
+        write(directory.path(), "Extra/readme.txt", b"hello");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(directory.path(), directory.path().join("Extra/loop")).unwrap();
+        write(
+            directory.path(),
+            "My Application Deployment.exe.manifest",
+            b"",
+        );
+
+        let mut root = Element::parse(include_bytes!("example/sample.exe.manifest")).unwrap();
+        let changes =
+            update_application_manifest(&mut root, directory.path(), DigestAlgorithm::Sha256)
+                .unwrap();
+        assert_eq!(changes.added, vec!["Extra\\readme.txt"]);
+        assert_eq!(changes.removed, vec!["CardDataSource\\CARDNAME_STRING.xml"]);
+        assert_eq!(changes.updated.len(), 3);
+
+        let application = root.to_document();
+        let manifest = ApplicationManifest::from_xml_bytes(application.as_bytes()).unwrap();
+        let files = &manifest.assembly.file;
+        let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "MyApplication.exe.config",
+                "CardDataSource\\client_DMU.xml",
+                "Extra\\readme.txt"
+            ]
+        );
+        assert_eq!(files[2].size, 5);
+        let hash = files[2].hash.as_ref().unwrap();
+        assert_eq!(
+            hash.dsig_digest_value.value,
+            base64::encode(DigestAlgorithm::Sha256.digest(b"hello"))
+        );
+        assert_eq!(
+            hash.dsig_digest_method.algorithm,
+            "http://www.w3.org/2000/09/xmldsig#sha256"
+        );
+        let assembly = manifest
+            .assembly
+            .dependency
+            .iter()
+            .filter_map(|d| d.dependent_assembly.as_ref())
+            .find(|a| a.codebase.is_some())
+            .unwrap();
+        assert_eq!(assembly.size, Some(10));
+
+        let mut root = Element::parse(include_bytes!("example/sample.application")).unwrap();
+        update_deployment_manifest(&mut root, application.as_bytes(), DigestAlgorithm::Sha256)
+            .unwrap();
+        let deployment = DeploymentManifest::from_xml_bytes(root.to_document().as_bytes()).unwrap();
+        assert!(deployment.assembly.signature.is_none());
+        let assembly = &deployment.assembly.dependency[0].dependent_assembly;
+        assert_eq!(assembly.size as usize, application.len());
+        let hash = assembly.hash.as_ref().unwrap();
+        assert_eq!(
+            hash.dsig_digest_value.value,
+            base64::encode(DigestAlgorithm::Sha256.digest(application.as_bytes()))
+        );
+    }
+}
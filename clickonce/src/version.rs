@@ -0,0 +1,82 @@
+//! Four part version numbers used throughout ClickOnce manifests
+use crate::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A version number in the format major.minor.build.revision
+///
+/// Missing trailing parts are treated as zero, so `1.2` and `1.2.0.0`
+/// compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Version {
+    /// Major version number
+    pub major: u32,
+    /// Minor version number
+    pub minor: u32,
+    /// Build number
+    pub build: u32,
+    /// Revision number
+    pub revision: u32,
+}
+
+impl Version {
+    /// Creates a version from its four parts.
+    pub fn new(major: u32, minor: u32, build: u32, revision: u32) -> Self {
+        Self {
+            major,
+            minor,
+            build,
+            revision,
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidVersion(s.to_string());
+
+        let mut parts = [0u32; 4];
+        for (index, part) in s.trim().split('.').enumerate() {
+            let slot = parts.get_mut(index).ok_or_else(invalid)?;
+            *slot = part.parse().map_err(|_| invalid())?;
+        }
+
+        Ok(Self::new(parts[0], parts[1], parts[2], parts[3]))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.build, self.revision
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_orders_versions() {
+        let old: Version = "3.4.96.6425".parse().unwrap();
+        let new: Version = "3.4.100.0".parse().unwrap();
+        assert!(old < new);
+        assert_eq!("1.2".parse::<Version>().unwrap(), Version::new(1, 2, 0, 0));
+        assert_eq!(new.to_string(), "3.4.100.0");
+    }
+
+    #[test]
+    fn rejects_invalid_versions() {
+        for version in &["", "1..2", "1.2.3.4.5", "a.b", "-1.0"] {
+            assert!(matches!(
+                version.parse::<Version>(),
+                Err(Error::InvalidVersion(_))
+            ));
+        }
+    }
+}
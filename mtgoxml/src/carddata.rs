@@ -0,0 +1,205 @@
+//! Finding the card data files in MTGO's application manifest
+//!
+//! The client ships its card database as XML alongside the executable. Each
+//! set has a definition file under `CardDataSource`, such as
+//! `CardDataSource\client_DMU.xml`, whose records refer by ID into string
+//! tables in the same folder named like `CARDNAME_STRING.xml`. Everything
+//! else in the manifest, including string tables of the user interface
+//! elsewhere, is program code and resources, and is not downloaded.
+use crate::Error;
+use clickonce::applicationmanifest::{ApplicationManifest, File};
+use clickonce::deploymentmanifest::Hash;
+
+/// Folder holding the card data in the application
+const CARD_DATA_FOLDER: &str = "CardDataSource";
+
+/// Suffix of the file stem of string tables
+const STRING_TABLE_SUFFIX: &str = "_STRING";
+
+/// Prefix of the file stem of set definitions, before the set code
+const SET_DEFINITION_PREFIX: &str = "client_";
+
+/// What a card data file holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardDataKind {
+    /// Card definitions for one set
+    SetDefinition,
+    /// A table of strings referred to by the set definitions
+    StringTable,
+}
+
+/// A card data file listed in the application manifest
+#[derive(Debug, Clone, Copy)]
+pub struct CardDataFile<'a> {
+    /// What the file holds
+    pub kind: CardDataKind,
+    /// The manifest's entry for the file
+    pub file: &'a File,
+}
+
+impl<'a> CardDataFile<'a> {
+    /// Classify a file from the manifest, returning `None` if it is not
+    /// card data.
+    pub fn classify(file: &'a File) -> Option<Self> {
+        let mut parts = file.name.split(['\\', '/']);
+        let file_name = parts.next_back()?;
+        if !parts.any(|part| part.eq_ignore_ascii_case(CARD_DATA_FOLDER)) {
+            return None;
+        }
+        let (kind, _) = classify_file_name(file_name)?;
+        Some(Self { kind, file })
+    }
+
+    /// The name of the file, relative to the application manifest.
+    pub fn name(&self) -> &'a str {
+        &self.file.name
+    }
+
+    /// Check downloaded contents against the size and hash in the manifest.
+    pub fn verify(&self, contents: &[u8]) -> Result<(), Error> {
+        check_contents(self.file.size, self.file.hash.as_ref(), contents).map_err(|reason| {
+            Error::FileMismatch {
+                name: self.file.name.clone(),
+                reason,
+            }
+        })
+    }
+}
+
+/// Lists the card data files in an application manifest, in manifest order.
+pub fn card_data_files(manifest: &ApplicationManifest) -> Vec<CardDataFile<'_>> {
+    manifest
+        .assembly
+        .file
+        .iter()
+        .filter_map(CardDataFile::classify)
+        .collect()
+}
+
+/// Classifies a file in the card data folder by its file name, returning
+/// its kind and its name without the `.xml` extension, or `None` if it is
+/// not card data.
+pub(crate) fn classify_file_name(file_name: &str) -> Option<(CardDataKind, &str)> {
+    let stem = strip_suffix_ignore_case(file_name, ".xml")?;
+    let kind = if strip_suffix_ignore_case(stem, STRING_TABLE_SUFFIX).is_some() {
+        CardDataKind::StringTable
+    } else {
+        CardDataKind::SetDefinition
+    };
+    Some((kind, stem))
+}
+
+/// The set code in the stem of a set definition's file name, such as `DMU`
+/// for `client_DMU`. A stem without the usual prefix is the code itself.
+pub(crate) fn set_code(stem: &str) -> &str {
+    match (
+        stem.get(..SET_DEFINITION_PREFIX.len()),
+        stem.get(SET_DEFINITION_PREFIX.len()..),
+    ) {
+        (Some(start), Some(code)) if start.eq_ignore_ascii_case(SET_DEFINITION_PREFIX) => code,
+        _ => stem,
+    }
+}
+
+/// Checks contents against a size and hash recorded in a manifest,
+/// describing the first difference found.
+pub(crate) fn check_contents(
+    size: u64,
+    hash: Option<&Hash>,
+    contents: &[u8],
+) -> Result<(), String> {
+    if size != contents.len() as u64 {
+        return Err(format!("expected {} bytes, got {}", size, contents.len()));
+    }
+    let hash = hash.ok_or_else(|| "manifest records no hash".to_string())?;
+    let algorithm = hash.algorithm().ok_or_else(|| {
+        format!(
+            "unsupported digest method {}",
+            hash.dsig_digest_method.algorithm
+        )
+    })?;
+    if !hash.matches(&algorithm.digest(contents)) {
+        return Err("hash differs".to_string());
+    }
+    Ok(())
+}
+
+fn strip_suffix_ignore_case<'a>(name: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = name.len().checked_sub(suffix.len())?;
+    match (name.get(..split), name.get(split..)) {
+        (Some(stem), Some(end)) if end.eq_ignore_ascii_case(suffix) => Some(stem),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clickonce::digest::DigestAlgorithm;
+    use clickonce::dom::Element;
+    use clickonce::update::update_application_manifest;
+    use clickonce::FromXML;
+
+    fn manifest() -> ApplicationManifest {
+        ApplicationManifest::from_xml_bytes(include_bytes!(
+            "../../clickonce/src/example/sample.exe.manifest"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn finds_card_data_files() {
+        let manifest = manifest();
+        let files: Vec<_> = card_data_files(&manifest)
+            .iter()
+            .map(|file| (file.name(), file.kind))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (
+                    "CardDataSource\\client_DMU.xml",
+                    CardDataKind::SetDefinition
+                ),
+                (
+                    "CardDataSource\\CARDNAME_STRING.xml",
+                    CardDataKind::StringTable
+                ),
+            ]
+        );
+
+        let mut manifest = manifest;
+        manifest.assembly.file[0].name = "Resources\\UI_STRING.xml".to_string();
+        assert!(CardDataFile::classify(&manifest.assembly.file[0]).is_none());
+    }
+
+    #[test]
+    fn verifies_downloads() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("CardDataSource");
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("client_DMU.xml"), b"<cards />").unwrap();
+        std::fs::write(path.join("CARDNAME_STRING.xml"), b"<strings />").unwrap();
+        std::fs::write(directory.path().join("MyApplication.exe"), b"executable").unwrap();
+        std::fs::write(directory.path().join("MyApplication.exe.config"), b"<a />").unwrap();
+
+        let mut root = Element::parse(include_bytes!(
+            "../../clickonce/src/example/sample.exe.manifest"
+        ))
+        .unwrap();
+        let _ = update_application_manifest(&mut root, directory.path(), DigestAlgorithm::Sha256)
+            .unwrap();
+        let manifest = ApplicationManifest::from_xml_bytes(root.to_document().as_bytes()).unwrap();
+        let files = card_data_files(&manifest);
+
+        files[0].verify(b"<cards />").unwrap();
+        assert!(matches!(
+            files[0].verify(b"<cardz />"),
+            Err(Error::FileMismatch { .. })
+        ));
+        assert!(matches!(
+            files[1].verify(b"<cards />"),
+            Err(Error::FileMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,479 @@
+//! Typed card records built from MTGO's card data XML
+//!
+//! Each set's definition file, such as `CardDataSource\client_DMU.xml`,
+//! holds a `<DigitalObject>` per catalog entry. Its children are properties
+//! with a `value` attribute; those named after a string table hold an ID
+//! into that table rather than the text itself:
+//!
+//! ```xml
+//! <DigitalObject DigitalObjectCatalogID="DOC_103001">
+//!   <CARDNAME_STRING value="ID1" />
+//!   <RARITY_STATUS value="R" />
+//! </DigitalObject>
+//!
+//! <CARDNAME_STRING>
+//!   <CARDNAME_STRING_ITEM id="ID1">Serra Paragon</CARDNAME_STRING_ITEM>
+//! </CARDNAME_STRING>
+//! ```
+//!
+//! [`StringTables`] loads the tables, and [`CardDatabase`] resolves every
+//! reference into them as set files are added. Cards remember the set file
+//! they came from, and the database summarises each as a [`Set`].
+use crate::carddata::{classify_file_name, set_code, CardDataKind};
+use crate::Error;
+use clickonce::dom::Element;
+use clickonce::ParseLimits;
+use mana::{Color, ManaCost};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use types::TypeLine;
+
+/// Property naming the card
+pub const NAME: &str = "CARDNAME_STRING";
+
+/// Property holding the card's type line
+pub const TYPE_LINE: &str = "CARDTYPE_STRING";
+
+/// Property holding the card's current Oracle text
+pub const ORACLE_TEXT: &str = "REAL_ORACLETEXT_STRING";
+
+/// Property holding the card's flavour text
+pub const FLAVOR_TEXT: &str = "FLAVORTEXT_STRING";
+
+/// Property naming the card's artist
+pub const ARTIST: &str = "ARTIST_NAME_STRING";
+
+/// Property naming the card's set
+pub const SET_NAME: &str = "CARDSETNAME_STRING";
+
+/// Element holding each catalog entry in a set file
+const DIGITAL_OBJECT: &str = "DigitalObject";
+
+/// Attribute of [`DIGITAL_OBJECT`] holding the catalog ID
+const CATALOG_ID: &str = "DigitalObjectCatalogID";
+
+/// Prefix of catalog IDs in [`CATALOG_ID`]
+const CATALOG_ID_PREFIX: &str = "DOC_";
+
+/// Suffix of the names of string properties
+const STRING_SUFFIX: &str = "_STRING";
+
+pub mod mana;
+pub mod types;
+
+/// The string tables that set files refer into, by table name
+#[derive(Debug, Clone, Default)]
+pub struct StringTables {
+    tables: HashMap<String, HashMap<String, String>>,
+}
+
+impl StringTables {
+    /// Create an empty set of tables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a table from raw file contents. The table is named after its root
+    /// element; adding a table of the same name again merges the two.
+    pub fn add(&mut self, contents: &[u8], limits: &ParseLimits) -> Result<(), Error> {
+        let root = Element::parse_with_limits(contents, limits)?;
+        let table = self.tables.entry(root.name.local_name.clone()).or_default();
+        for item in root.elements() {
+            let id = item.attribute("id").ok_or_else(|| {
+                Error::CardData(format!(
+                    "{} entry {} has no id",
+                    root.name.local_name, item.name.local_name
+                ))
+            })?;
+            let _ = table.insert(id.to_string(), item.text());
+        }
+        Ok(())
+    }
+
+    /// Look up a string by table name and ID.
+    pub fn get(&self, table: &str, id: &str) -> Option<&str> {
+        self.tables.get(table)?.get(id).map(String::as_str)
+    }
+
+    /// Resolve a reference, failing if the table or ID is unknown.
+    fn resolve(&self, table: &str, id: &str) -> Result<&str, Error> {
+        self.get(table, id)
+            .ok_or_else(|| Error::CardData(format!("{} has no string {}", table, id)))
+    }
+}
+
+/// A catalog entry from a set file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Card {
+    /// The MTGO catalog ID
+    pub catalog_id: u32,
+    /// Code of the set the card came from, such as `DMU`
+    pub set: String,
+    /// The card's name
+    pub name: String,
+    /// The card's type line as MTGO gives it, such as `Creature — Elf Druid`
+    pub type_line_text: Option<String>,
+    /// The card's type line split into types, with one per face where MTGO
+    /// combines the lines of several faces with ` // `
+    pub type_lines: Vec<TypeLine>,
+    /// The card's current Oracle text
+    pub oracle_text: Option<String>,
+    /// The card's flavour text
+    pub flavor_text: Option<String>,
+    /// The card's artist
+    pub artist: Option<String>,
+    /// Every other property of the entry, with string references resolved
+    pub properties: BTreeMap<String, String>,
+}
+
+impl Card {
+    /// Build a card in `set` from a `<DigitalObject>` element, resolving
+    /// references into `strings`.
+    pub fn from_element(
+        set: &str,
+        element: &Element,
+        strings: &StringTables,
+    ) -> Result<Self, Error> {
+        let catalog_id = element
+            .attribute(CATALOG_ID)
+            .ok_or_else(|| Error::CardData(format!("{} has no {}", DIGITAL_OBJECT, CATALOG_ID)))?;
+        let catalog_id = parse_catalog_id(catalog_id)?;
+
+        let mut properties = BTreeMap::new();
+        for property in element.elements() {
+            let name = &property.name.local_name;
+            let value = match property.attribute("value") {
+                Some(value) if name.ends_with(STRING_SUFFIX) => strings.resolve(name, value)?,
+                Some(value) => value,
+                None => continue,
+            };
+            let _ = properties.insert(name.clone(), value.to_string());
+        }
+
+        let name = properties
+            .remove(NAME)
+            .ok_or_else(|| Error::CardData(format!("catalog ID {} has no {}", catalog_id, NAME)))?;
+        let type_line_text = properties.remove(TYPE_LINE);
+        Ok(Self {
+            catalog_id,
+            set: set.to_string(),
+            name,
+            type_lines: type_line_text
+                .as_deref()
+                .map(TypeLine::split_faces)
+                .unwrap_or_default(),
+            type_line_text,
+            oracle_text: properties.remove(ORACLE_TEXT),
+            flavor_text: properties.remove(FLAVOR_TEXT),
+            artist: properties.remove(ARTIST),
+            properties,
+        })
+    }
+
+    /// The card's colour identity, in WUBRG order: the colours of its mana
+    /// cost and of the mana symbols in its Oracle text. The card data gives
+    /// no mana cost that this reads, so pass the card's cost if it is known.
+    pub fn color_identity(&self, mana_cost: Option<&ManaCost>) -> Vec<Color> {
+        let text = self
+            .oracle_text
+            .as_deref()
+            .map(mana::text_symbols)
+            .unwrap_or_default();
+        let cost = mana_cost
+            .map(|cost| cost.symbols.as_slice())
+            .unwrap_or_default();
+        mana::colors_of(cost.iter().chain(&text))
+    }
+
+    /// The value of another property of the entry.
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).map(String::as_str)
+    }
+}
+
+/// A summary of the cards from one set file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Set {
+    /// The set's code, from the name of its file
+    pub code: String,
+    /// The set's name, if its cards give one
+    pub name: Option<String>,
+    /// Position of the set in release order, counting from zero for the
+    /// earliest. MTGO assigns catalog IDs in increasing order as sets are
+    /// added, so sets are ordered by their lowest catalog ID.
+    pub release_order: usize,
+    /// Number of cards in the set
+    pub card_count: usize,
+}
+
+/// Cards from any number of set files, keyed by catalog ID
+#[derive(Debug, Clone, Default)]
+pub struct CardDatabase {
+    cards: BTreeMap<u32, Card>,
+}
+
+impl CardDatabase {
+    /// Create an empty database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every set file and string table in a `CardDataSource` folder,
+    /// such as one written by [`crate::MtgoApplication::download_card_data`].
+    pub fn from_directory<P: AsRef<Path>>(path: P, limits: &ParseLimits) -> Result<Self, Error> {
+        let mut set_files: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut strings = StringTables::new();
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in paths {
+            let (kind, stem) = match path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(classify_file_name)
+            {
+                Some(classified) => classified,
+                None => continue,
+            };
+            let contents = limits.read_to_end(std::fs::File::open(&path)?)?;
+            match kind {
+                CardDataKind::StringTable => strings.add(&contents, limits)?,
+                CardDataKind::SetDefinition => {
+                    set_files.push((set_code(stem).to_string(), contents))
+                }
+            }
+        }
+
+        let mut database = Self::new();
+        for (code, contents) in set_files {
+            let _ = database.add_set_file(&code, &contents, &strings, limits)?;
+        }
+        Ok(database)
+    }
+
+    /// Add the cards in the file for the set with `code`, returning how many
+    /// were added. A card with the catalog ID of one already present
+    /// replaces it. If any entry cannot be read, none are added.
+    pub fn add_set_file(
+        &mut self,
+        code: &str,
+        contents: &[u8],
+        strings: &StringTables,
+        limits: &ParseLimits,
+    ) -> Result<usize, Error> {
+        let root = Element::parse_with_limits(contents, limits)?;
+        let cards = root
+            .elements()
+            .filter(|e| e.name.local_name == DIGITAL_OBJECT)
+            .map(|element| Card::from_element(code, element, strings))
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = cards.len();
+        for card in cards {
+            self.insert(card);
+        }
+        Ok(count)
+    }
+
+    /// Add a card, replacing any with the same catalog ID.
+    fn insert(&mut self, card: Card) {
+        let _ = self.cards.insert(card.catalog_id, card);
+    }
+
+    /// The card with a catalog ID.
+    pub fn get(&self, catalog_id: u32) -> Option<&Card> {
+        self.cards.get(&catalog_id)
+    }
+
+    /// Every card, in catalog ID order.
+    pub fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.cards.values()
+    }
+
+    /// The number of cards.
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Whether there are no cards.
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Every set with cards in the database, in release order.
+    pub fn sets(&self) -> Vec<Set> {
+        // Cards are visited in catalog ID order, so each set is first seen
+        // at its lowest catalog ID.
+        let mut sets: Vec<Set> = Vec::new();
+        for card in self.cards.values() {
+            match sets.iter_mut().find(|set| set.code == card.set) {
+                Some(set) => {
+                    set.card_count += 1;
+                    if set.name.is_none() {
+                        set.name = card.property(SET_NAME).map(str::to_string);
+                    }
+                }
+                None => sets.push(Set {
+                    code: card.set.clone(),
+                    name: card.property(SET_NAME).map(str::to_string),
+                    release_order: sets.len(),
+                    card_count: 1,
+                }),
+            }
+        }
+        sets
+    }
+
+    /// The set with a code, compared case-insensitively.
+    pub fn set(&self, code: &str) -> Option<Set> {
+        self.sets()
+            .into_iter()
+            .find(|set| set.code.eq_ignore_ascii_case(code))
+    }
+
+    /// A database of only the cards in the set with a code, compared
+    /// case-insensitively.
+    pub fn filter_by_set(&self, code: &str) -> CardDatabase {
+        let mut database = CardDatabase::new();
+        for card in self.cards.values() {
+            if card.set.eq_ignore_ascii_case(code) {
+                database.insert(card.clone());
+            }
+        }
+        database
+    }
+}
+
+/// Parses a catalog ID such as `DOC_103001`.
+fn parse_catalog_id(value: &str) -> Result<u32, Error> {
+    value
+        .strip_prefix(CATALOG_ID_PREFIX)
+        .unwrap_or(value)
+        .parse()
+        .map_err(|_| Error::CardData(format!("invalid catalog ID {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> CardDatabase {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/example/CardDataSource");
+        CardDatabase::from_directory(path, &ParseLimits::default()).unwrap()
+    }
+
+    #[test]
+    fn resolves_string_references() {
+        let database = database();
+        assert_eq!(database.len(), 9);
+
+        let card = database.get(103001).unwrap();
+        assert_eq!(card.name, "Serra Paragon");
+        assert_eq!(card.set, "DMU");
+        assert_eq!(card.type_line_text.as_deref(), Some("Creature — Angel"));
+        assert_eq!(card.type_lines.len(), 1);
+        let type_line = &card.type_lines[0];
+        assert_eq!(type_line.card_types, vec!["Creature"]);
+        assert_eq!(type_line.subtypes, vec!["Angel"]);
+        assert!(card.oracle_text.as_deref().unwrap().starts_with("Flying\n"));
+        assert_eq!(
+            card.flavor_text.as_deref(),
+            Some("\"Benalia will not fall while I have wings to fly.\"")
+        );
+        assert_eq!(card.artist.as_deref(), Some("Sidharth Chaturvedi"));
+        assert_eq!(card.property("RARITY_STATUS"), Some("R"));
+
+        let card = database.get(103003).unwrap();
+        assert_eq!(card.name, "Plains");
+        assert_eq!(card.oracle_text, None);
+    }
+
+    #[test]
+    fn finds_color_identity() {
+        let database = database();
+        let cost: ManaCost = "3WW".parse().unwrap();
+        let serra_paragon = database.get(103001).unwrap();
+        assert_eq!(
+            serra_paragon.color_identity(Some(&cost)),
+            vec![Color::White]
+        );
+        assert!(serra_paragon.color_identity(None).is_empty());
+
+        // Llanowar Elves taps for {G}
+        let llanowar_elves = database.get(103002).unwrap();
+        assert_eq!(llanowar_elves.color_identity(None), vec![Color::Green]);
+        let cost: ManaCost = "U".parse().unwrap();
+        assert_eq!(
+            llanowar_elves.color_identity(Some(&cost)),
+            vec![Color::Blue, Color::Green]
+        );
+    }
+
+    #[test]
+    fn rejects_unresolved_references() {
+        let mut strings = StringTables::new();
+        strings
+            .add(
+                b"<CARDNAME_STRING><CARDNAME_STRING_ITEM id=\"ID1\">Fire</CARDNAME_STRING_ITEM>\
+                  </CARDNAME_STRING>",
+                &ParseLimits::default(),
+            )
+            .unwrap();
+        let mut database = CardDatabase::new();
+        let result = database.add_set_file(
+            "DMU",
+            b"<CARDDATA><DigitalObject DigitalObjectCatalogID=\"DOC_1\">\
+              <CARDNAME_STRING value=\"ID1\" /></DigitalObject>\
+              <DigitalObject DigitalObjectCatalogID=\"DOC_2\">\
+              <CARDNAME_STRING value=\"ID2\" /></DigitalObject></CARDDATA>",
+            &strings,
+            &ParseLimits::default(),
+        );
+        assert!(matches!(result, Err(Error::CardData(_))));
+        assert!(database.is_empty());
+    }
+
+    #[test]
+    fn groups_cards_by_set() {
+        let database = database();
+        assert_eq!(
+            database.sets(),
+            vec![
+                Set {
+                    code: "DMU".to_string(),
+                    name: Some("Dominaria United".to_string()),
+                    release_order: 0,
+                    card_count: 5,
+                },
+                Set {
+                    code: "BRO".to_string(),
+                    name: Some("The Brothers' War".to_string()),
+                    release_order: 1,
+                    card_count: 4,
+                },
+            ]
+        );
+        assert_eq!(database.set("bro").unwrap().release_order, 1);
+        assert_eq!(database.set("ZNR"), None);
+
+        let brothers_war = database.filter_by_set("BRO");
+        let names: Vec<_> = brothers_war
+            .cards()
+            .map(|card| card.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "Urza, Lord Protector",
+                "Mishra's Foundry",
+                "The Mightstone and Weakstone",
+                "Urza, Planeswalker"
+            ]
+        );
+        assert!(database.filter_by_set("ZNR").is_empty());
+    }
+}
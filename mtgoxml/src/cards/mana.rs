@@ -0,0 +1,373 @@
+//! Mana costs and colours
+//!
+//! MTGO encodes a mana cost as a run of symbols, either bare, as in `2WU`,
+//! or each in braces or parentheses, as in `{2}{W}{U}` or `(W/U)`. Symbols
+//! with more than one character, such as hybrid `W/U`, Phyrexian `W/P` or
+//! generic hybrid `2/W`, must be bracketed. [`ManaCost`] parses any of
+//! these and renders the canonical braced form.
+use crate::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// One of the five colours of Magic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Color {
+    /// White, `W`
+    White,
+    /// Blue, `U`
+    Blue,
+    /// Black, `B`
+    Black,
+    /// Red, `R`
+    Red,
+    /// Green, `G`
+    Green,
+}
+
+impl Color {
+    /// Every colour, in the conventional WUBRG order.
+    pub const ALL: [Color; 5] = [
+        Color::White,
+        Color::Blue,
+        Color::Black,
+        Color::Red,
+        Color::Green,
+    ];
+
+    /// The colour's letter in mana symbols.
+    pub fn symbol(self) -> char {
+        match self {
+            Color::White => 'W',
+            Color::Blue => 'U',
+            Color::Black => 'B',
+            Color::Red => 'R',
+            Color::Green => 'G',
+        }
+    }
+
+    /// The colour with a letter, in either case.
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|color| color.symbol() == symbol.to_ascii_uppercase())
+    }
+}
+
+/// A symbol in a mana cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManaSymbol {
+    /// A number of generic mana, such as `{2}`
+    Generic(u32),
+    /// A variable amount of generic mana, `{X}`, `{Y}` or `{Z}`
+    Variable(char),
+    /// One mana of a colour, such as `{W}`
+    Colored(Color),
+    /// One colourless mana, `{C}`
+    Colorless,
+    /// One mana from a snow source, `{S}`
+    Snow,
+    /// One mana of either colour, such as `{W/U}`
+    Hybrid(Color, Color),
+    /// Generic mana or one of a colour, such as `{2/W}`
+    GenericHybrid(u32, Color),
+    /// One mana of a colour or 2 life, such as `{W/P}`
+    Phyrexian(Color),
+    /// One mana of either colour or 2 life, such as `{W/U/P}`
+    PhyrexianHybrid(Color, Color),
+}
+
+impl ManaSymbol {
+    /// The symbol's contribution to mana value. Variable symbols count as
+    /// zero, and hybrid symbols as their larger half.
+    pub fn mana_value(self) -> u32 {
+        match self {
+            ManaSymbol::Generic(amount) => amount,
+            ManaSymbol::Variable(_) => 0,
+            ManaSymbol::GenericHybrid(amount, _) => amount.max(1),
+            _ => 1,
+        }
+    }
+
+    /// The colours of the symbol.
+    pub fn colors(self) -> Vec<Color> {
+        match self {
+            ManaSymbol::Colored(color)
+            | ManaSymbol::GenericHybrid(_, color)
+            | ManaSymbol::Phyrexian(color) => vec![color],
+            ManaSymbol::Hybrid(first, second) | ManaSymbol::PhyrexianHybrid(first, second) => {
+                vec![first, second]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl FromStr for ManaSymbol {
+    type Err = Error;
+
+    /// Parses the text of a symbol, without its brackets.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidManaCost(s.to_string());
+        let parts: Vec<&str> = s.split('/').map(str::trim).collect();
+        let color = |part: &str| {
+            let mut chars = part.chars();
+            match (chars.next(), chars.next()) {
+                (Some(symbol), None) => Color::from_symbol(symbol),
+                _ => None,
+            }
+        };
+        let phyrexian = |part: &str| part.eq_ignore_ascii_case("P");
+
+        let symbol = match parts.as_slice() {
+            [single] => {
+                if let Ok(amount) = single.parse() {
+                    ManaSymbol::Generic(amount)
+                } else if let Some(color) = color(single) {
+                    ManaSymbol::Colored(color)
+                } else {
+                    match single.to_ascii_uppercase().as_str() {
+                        "C" => ManaSymbol::Colorless,
+                        "S" => ManaSymbol::Snow,
+                        "X" => ManaSymbol::Variable('X'),
+                        "Y" => ManaSymbol::Variable('Y'),
+                        "Z" => ManaSymbol::Variable('Z'),
+                        _ => return Err(invalid()),
+                    }
+                }
+            }
+            [first, second] if phyrexian(second) => {
+                ManaSymbol::Phyrexian(color(first).ok_or_else(invalid)?)
+            }
+            [first, second] => match (first.parse(), color(first), color(second)) {
+                (Ok(amount), _, Some(second)) => ManaSymbol::GenericHybrid(amount, second),
+                (_, Some(first), Some(second)) if first != second => {
+                    ManaSymbol::Hybrid(first, second)
+                }
+                _ => return Err(invalid()),
+            },
+            [first, second, third] if phyrexian(third) => match (color(first), color(second)) {
+                (Some(first), Some(second)) if first != second => {
+                    ManaSymbol::PhyrexianHybrid(first, second)
+                }
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(symbol)
+    }
+}
+
+impl fmt::Display for ManaSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManaSymbol::Generic(amount) => write!(f, "{{{}}}", amount),
+            ManaSymbol::Variable(variable) => write!(f, "{{{}}}", variable),
+            ManaSymbol::Colored(color) => write!(f, "{{{}}}", color.symbol()),
+            ManaSymbol::Colorless => write!(f, "{{C}}"),
+            ManaSymbol::Snow => write!(f, "{{S}}"),
+            ManaSymbol::Hybrid(first, second) => {
+                write!(f, "{{{}/{}}}", first.symbol(), second.symbol())
+            }
+            ManaSymbol::GenericHybrid(amount, color) => {
+                write!(f, "{{{}/{}}}", amount, color.symbol())
+            }
+            ManaSymbol::Phyrexian(color) => write!(f, "{{{}/P}}", color.symbol()),
+            ManaSymbol::PhyrexianHybrid(first, second) => {
+                write!(f, "{{{}/{}/P}}", first.symbol(), second.symbol())
+            }
+        }
+    }
+}
+
+/// A mana cost, as a sequence of symbols in printed order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManaCost {
+    /// The symbols of the cost
+    pub symbols: Vec<ManaSymbol>,
+}
+
+impl ManaCost {
+    /// The mana value (formerly converted mana cost) of the cost, saturating
+    /// at `u32::MAX`.
+    pub fn mana_value(&self) -> u32 {
+        self.symbols
+            .iter()
+            .fold(0, |total, symbol| total.saturating_add(symbol.mana_value()))
+    }
+
+    /// The colours of the symbols in the cost, in WUBRG order. A card's
+    /// colour identity also includes symbols in its rules text; see
+    /// [`crate::cards::Card::color_identity`].
+    pub fn colors(&self) -> Vec<Color> {
+        colors_of(&self.symbols)
+    }
+
+    /// Whether the cost has no symbols, as for lands.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+impl FromStr for ManaCost {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidManaCost(s.to_string());
+        let mut symbols = Vec::new();
+        let mut rest = s.trim();
+        while let Some(c) = rest.chars().next() {
+            let close = match c {
+                '{' => Some('}'),
+                '(' => Some(')'),
+                _ => None,
+            };
+            let length = match close {
+                Some(close) => {
+                    let end = rest.find(close).ok_or_else(invalid)?;
+                    symbols.push(rest[1..end].parse()?);
+                    end + 1
+                }
+                None if c.is_ascii_digit() => {
+                    let end = rest
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(rest.len());
+                    symbols.push(ManaSymbol::Generic(
+                        rest[..end].parse().map_err(|_| invalid())?,
+                    ));
+                    end
+                }
+                None if c.is_whitespace() => c.len_utf8(),
+                None => {
+                    symbols.push(rest[..c.len_utf8()].parse()?);
+                    c.len_utf8()
+                }
+            };
+            rest = &rest[length..];
+        }
+        Ok(Self { symbols })
+    }
+}
+
+impl fmt::Display for ManaCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for symbol in &self.symbols {
+            write!(f, "{}", symbol)?;
+        }
+        Ok(())
+    }
+}
+
+/// The mana symbols in rules text, such as `{G}` in `{T}: Add {G}.`, in
+/// order. Reminder text in parentheses is left out, as are symbols that are
+/// not mana, such as `{T}`.
+pub fn text_symbols(text: &str) -> Vec<ManaSymbol> {
+    let mut symbols = Vec::new();
+    let mut reminder_depth = 0usize;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let mut length = c.len_utf8();
+        match c {
+            '(' => reminder_depth += 1,
+            ')' => reminder_depth = reminder_depth.saturating_sub(1),
+            '{' => {
+                if let Some(end) = rest.find('}') {
+                    if reminder_depth == 0 {
+                        if let Ok(symbol) = rest[1..end].parse() {
+                            symbols.push(symbol);
+                        }
+                    }
+                    length = end + 1;
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[length..];
+    }
+    symbols
+}
+
+/// The distinct colours of some symbols, in WUBRG order.
+pub(crate) fn colors_of<'a, I>(symbols: I) -> Vec<Color>
+where
+    I: IntoIterator<Item = &'a ManaSymbol>,
+{
+    let mut colors: Vec<Color> = symbols
+        .into_iter()
+        .flat_map(|symbol| symbol.colors())
+        .collect();
+    colors.sort();
+    colors.dedup();
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_encoded_costs() {
+        let cost: ManaCost = "2WU".parse().unwrap();
+        assert_eq!(
+            cost.symbols,
+            vec![
+                ManaSymbol::Generic(2),
+                ManaSymbol::Colored(Color::White),
+                ManaSymbol::Colored(Color::Blue),
+            ]
+        );
+        assert_eq!(cost.to_string(), "{2}{W}{U}");
+        assert_eq!(cost.mana_value(), 4);
+        assert_eq!(cost.colors(), vec![Color::White, Color::Blue]);
+        assert_eq!("{2}{W}{U}".parse::<ManaCost>().unwrap(), cost);
+
+        let cost: ManaCost = "XX(G/U)(2/W){B/P}{R/G/P}{S}{C}12".parse().unwrap();
+        assert_eq!(cost.to_string(), "{X}{X}{G/U}{2/W}{B/P}{R/G/P}{S}{C}{12}");
+        assert_eq!(cost.mana_value(), 1 + 2 + 1 + 1 + 1 + 1 + 12);
+        assert_eq!(
+            cost.colors(),
+            vec![
+                Color::White,
+                Color::Blue,
+                Color::Black,
+                Color::Red,
+                Color::Green
+            ]
+        );
+
+        let huge: ManaCost = "{4294967295}{1}".parse().unwrap();
+        assert_eq!(huge.mana_value(), u32::MAX);
+
+        let land: ManaCost = "".parse().unwrap();
+        assert!(land.is_empty());
+        assert_eq!(land.mana_value(), 0);
+        assert_eq!(land.to_string(), "");
+    }
+
+    #[test]
+    fn finds_symbols_in_text() {
+        assert_eq!(
+            text_symbols("{T}: Add {G}."),
+            vec![ManaSymbol::Colored(Color::Green)]
+        );
+        assert_eq!(
+            text_symbols("{2}{R/W}, {Q}: Draw a card. ({U} and {B} are reminders.)"),
+            vec![
+                ManaSymbol::Generic(2),
+                ManaSymbol::Hybrid(Color::Red, Color::White)
+            ]
+        );
+        assert!(text_symbols("Flying {unclosed").is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_symbols() {
+        for cost in &["{Q}", "{W/W}", "{2/P/W}", "{W", "H", "{W/U/B}"] {
+            assert!(
+                matches!(cost.parse::<ManaCost>(), Err(Error::InvalidManaCost(_))),
+                "{}",
+                cost
+            );
+        }
+    }
+}
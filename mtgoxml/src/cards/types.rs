@@ -0,0 +1,208 @@
+//! Type lines split into supertypes, card types and subtypes
+//!
+//! A type line such as `Legendary Creature — Human Artificer` lists
+//! supertypes and card types before the dash, and subtypes after it. The
+//! words before the dash come from a small, stable vocabulary, so any word
+//! outside it is kept in [`TypeLine::unknown`] to flag data errors or new
+//! types. Subtypes are open-ended, with new creature types added in most
+//! sets, and are not checked.
+//!
+//! A card with several faces may have their type lines combined, as in
+//! `Creature — Giant // Sorcery — Adventure`; [`TypeLine::split_faces`]
+//! splits these into one type line per face.
+use std::fmt;
+
+/// Supertypes, from the Comprehensive Rules
+pub const SUPERTYPES: &[&str] = &["Basic", "Legendary", "Ongoing", "Snow", "World"];
+
+/// Card types, from the Comprehensive Rules
+pub const CARD_TYPES: &[&str] = &[
+    "Artifact",
+    "Battle",
+    "Conspiracy",
+    "Creature",
+    "Dungeon",
+    "Enchantment",
+    "Instant",
+    "Kindred",
+    "Land",
+    "Phenomenon",
+    "Plane",
+    "Planeswalker",
+    "Scheme",
+    "Sorcery",
+    "Tribal",
+    "Vanguard",
+];
+
+/// Separators between the types and subtypes, as MTGO and other sources
+/// write them
+const SEPARATORS: &[&str] = &["—", "–", " - "];
+
+/// Separator between the type lines of the faces of a card
+const FACE_SEPARATOR: &str = " // ";
+
+/// A card's type line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeLine {
+    /// Supertypes, such as `Legendary`
+    pub supertypes: Vec<String>,
+    /// Card types, such as `Creature`
+    pub card_types: Vec<String>,
+    /// Subtypes, such as `Elf` and `Druid`
+    pub subtypes: Vec<String>,
+    /// Words before the dash that are neither supertypes nor card types
+    pub unknown: Vec<String>,
+}
+
+impl TypeLine {
+    /// Splits a type line that may combine the lines of several faces
+    /// with ` // `, giving one type line per face.
+    pub fn split_faces(s: &str) -> Vec<TypeLine> {
+        s.split(FACE_SEPARATOR).map(TypeLine::from).collect()
+    }
+
+    /// Whether every word before the dash is a known supertype or card type.
+    pub fn is_valid(&self) -> bool {
+        self.unknown.is_empty() && !self.card_types.is_empty()
+    }
+
+    /// Whether the type line has a supertype, compared case-insensitively.
+    pub fn has_supertype(&self, supertype: &str) -> bool {
+        contains(&self.supertypes, supertype)
+    }
+
+    /// Whether the type line has a card type, compared case-insensitively.
+    pub fn has_card_type(&self, card_type: &str) -> bool {
+        contains(&self.card_types, card_type)
+    }
+
+    /// Whether the type line has a subtype, compared case-insensitively.
+    pub fn has_subtype(&self, subtype: &str) -> bool {
+        contains(&self.subtypes, subtype)
+    }
+}
+
+impl From<&str> for TypeLine {
+    /// Splits the type line of one face. Unrecognised words before the
+    /// dash are collected in [`TypeLine::unknown`], and a line combining
+    /// several faces is left whole there; use [`TypeLine::split_faces`] for
+    /// those.
+    fn from(s: &str) -> Self {
+        if s.contains(FACE_SEPARATOR) {
+            return TypeLine {
+                unknown: vec![s.to_string()],
+                ..TypeLine::default()
+            };
+        }
+        let (types, subtypes) = SEPARATORS
+            .iter()
+            .find_map(|separator| s.split_once(separator))
+            .unwrap_or((s, ""));
+
+        let mut type_line = TypeLine {
+            subtypes: subtypes.split_whitespace().map(str::to_string).collect(),
+            ..TypeLine::default()
+        };
+        for word in types.split_whitespace() {
+            if let Some(supertype) = canonical(SUPERTYPES, word) {
+                type_line.supertypes.push(supertype.to_string());
+            } else if let Some(card_type) = canonical(CARD_TYPES, word) {
+                type_line.card_types.push(card_type.to_string());
+            } else {
+                type_line.unknown.push(word.to_string());
+            }
+        }
+        type_line
+    }
+}
+
+impl fmt::Display for TypeLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let types: Vec<&str> = self
+            .supertypes
+            .iter()
+            .chain(&self.card_types)
+            .chain(&self.unknown)
+            .map(String::as_str)
+            .collect();
+        write!(f, "{}", types.join(" "))?;
+        if !self.subtypes.is_empty() {
+            write!(f, " — {}", self.subtypes.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The vocabulary's spelling of a word, if it is in the vocabulary.
+fn canonical(vocabulary: &[&'static str], word: &str) -> Option<&'static str> {
+    vocabulary
+        .iter()
+        .copied()
+        .find(|known| known.eq_ignore_ascii_case(word))
+}
+
+fn contains(words: &[String], word: &str) -> bool {
+    words.iter().any(|known| known.eq_ignore_ascii_case(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_type_lines() {
+        let type_line = TypeLine::from("Legendary Creature — Human Artificer");
+        assert_eq!(type_line.supertypes, vec!["Legendary"]);
+        assert_eq!(type_line.card_types, vec!["Creature"]);
+        assert_eq!(type_line.subtypes, vec!["Human", "Artificer"]);
+        assert!(type_line.is_valid());
+        assert!(type_line.has_supertype("legendary"));
+        assert!(type_line.has_card_type("Creature"));
+        assert!(type_line.has_subtype("Human"));
+        assert_eq!(
+            type_line.to_string(),
+            "Legendary Creature — Human Artificer"
+        );
+
+        let type_line = TypeLine::from("basic snow land - forest");
+        assert_eq!(type_line.supertypes, vec!["Basic", "Snow"]);
+        assert_eq!(type_line.card_types, vec!["Land"]);
+        assert_eq!(type_line.to_string(), "Basic Snow Land — forest");
+
+        let type_line = TypeLine::from("Artifact Land");
+        assert_eq!(type_line.card_types, vec!["Artifact", "Land"]);
+        assert!(type_line.subtypes.is_empty());
+    }
+
+    #[test]
+    fn flags_unknown_types() {
+        let type_line = TypeLine::from("Legendary Creatur — Elf");
+        assert_eq!(type_line.unknown, vec!["Creatur"]);
+        assert!(!type_line.is_valid());
+
+        let type_line = TypeLine::from("Legendary");
+        assert!(!type_line.is_valid());
+
+        let type_line = TypeLine::from("Creature — Giant // Sorcery — Adventure");
+        assert!(type_line.subtypes.is_empty());
+        assert!(!type_line.is_valid());
+    }
+
+    #[test]
+    fn splits_combined_faces() {
+        let faces = TypeLine::split_faces("Creature — Giant // Sorcery — Adventure");
+        assert_eq!(faces.len(), 2);
+        assert_eq!(faces[0].card_types, vec!["Creature"]);
+        assert_eq!(faces[0].subtypes, vec!["Giant"]);
+        assert_eq!(faces[1].card_types, vec!["Sorcery"]);
+        assert_eq!(faces[1].subtypes, vec!["Adventure"]);
+        assert!(faces.iter().all(TypeLine::is_valid));
+
+        let faces = TypeLine::split_faces("Legendary Creature — Human Artificer");
+        assert_eq!(
+            faces,
+            vec![TypeLine::from("Legendary Creature — Human Artificer")]
+        );
+    }
+}
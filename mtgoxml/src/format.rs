@@ -0,0 +1,229 @@
+//! Canonical formatting of MTGO's XML data files
+//!
+//! Successive releases of the client re-export the card data with records
+//! and attributes in whatever order the exporter produced them, with mixed
+//! line endings and encodings. [`Formatter`] rewrites a file into one stable
+//! form, so that committing each release gives small, readable diffs:
+//!
+//! - the output is UTF-8 with `\n` line endings, indented one element per
+//!   line;
+//! - attributes are sorted by name;
+//! - sibling elements that all carry an ID attribute are sorted by it, with
+//!   the digits in IDs compared as numbers so `ID9` sorts before `ID10`;
+//! - comments and processing instructions are removed, as are any
+//!   attributes configured as volatile.
+use crate::Error;
+use clickonce::dom::{Element, Node};
+use clickonce::ParseLimits;
+use std::cmp::Ordering;
+
+/// Options for formatting XML data files
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    /// Indentation added for each level of nesting.
+    pub indent: String,
+
+    /// Attributes that identify a record, in order of preference. Siblings
+    /// are sorted by the first of these they all carry.
+    pub id_attributes: Vec<String>,
+
+    /// Attributes that change between exports without the data changing,
+    /// removed wherever they appear. Names are matched exactly. None are
+    /// known, so this is empty by default.
+    pub volatile_attributes: Vec<String>,
+
+    /// Limits applied when parsing files.
+    pub limits: ParseLimits,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self {
+            indent: "  ".to_string(),
+            id_attributes: vec!["id".to_string(), "DigitalObjectCatalogID".to_string()],
+            volatile_attributes: Vec::new(),
+            limits: ParseLimits::default(),
+        }
+    }
+}
+
+impl Formatter {
+    /// Format a file's raw contents, in any supported encoding.
+    pub fn format(&self, contents: &[u8]) -> Result<String, Error> {
+        let mut root = Element::parse_with_limits(contents, &self.limits)?;
+        self.canonicalize(&mut root);
+        Ok(root.to_pretty_document(&self.indent))
+    }
+
+    /// Put an element and everything inside it into canonical order,
+    /// removing noise.
+    pub fn canonicalize(&self, element: &mut Element) {
+        element.attributes.retain(|attribute| {
+            attribute.name.namespace.is_some()
+                || !self
+                    .volatile_attributes
+                    .contains(&attribute.name.local_name)
+        });
+        element.attributes.sort_by(|a, b| {
+            (a.name.namespace.as_deref(), &a.name.local_name)
+                .cmp(&(b.name.namespace.as_deref(), &b.name.local_name))
+        });
+
+        element
+            .children
+            .retain(|node| !matches!(node, Node::Comment(_) | Node::ProcessingInstruction { .. }));
+        for node in &mut element.children {
+            if let Node::Element(child) = node {
+                self.canonicalize(child);
+            }
+        }
+        self.sort_records(element);
+    }
+
+    /// Sorts the child elements by ID, if they are all records with one.
+    /// Text between them must be whitespace, which the formatted output
+    /// drops anyway.
+    fn sort_records(&self, element: &mut Element) {
+        let only_elements = element.children.iter().all(|node| match node {
+            Node::Element(_) => true,
+            Node::Text(text) => text.trim().is_empty(),
+            _ => false,
+        });
+        if !only_elements || element.elements().count() < 2 {
+            return;
+        }
+        let id = match self.id_attributes.iter().find(|id| {
+            element
+                .elements()
+                .all(|child| child.attribute(id).is_some())
+        }) {
+            Some(id) => id,
+            None => return,
+        };
+
+        let mut records: Vec<Element> = element
+            .children
+            .drain(..)
+            .filter_map(|node| match node {
+                Node::Element(child) => Some(child),
+                _ => None,
+            })
+            .collect();
+        records.sort_by(|a, b| {
+            compare_ids(
+                a.attribute(id).unwrap_or_default(),
+                b.attribute(id).unwrap_or_default(),
+            )
+        });
+        element.children = records.into_iter().map(Node::Element).collect();
+    }
+}
+
+/// Compares IDs with runs of digits ordered by their numeric value.
+fn compare_ids(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (a_digits, b_digits) = (digit_prefix(a), digit_prefix(b));
+        let ordering = if !a_digits.is_empty() && !b_digits.is_empty() {
+            let (a_number, b_number) = (
+                a_digits.trim_start_matches('0'),
+                b_digits.trim_start_matches('0'),
+            );
+            a_number
+                .len()
+                .cmp(&b_number.len())
+                .then_with(|| a_number.cmp(b_number))
+                .then_with(|| a_digits.len().cmp(&b_digits.len()))
+        } else {
+            match (a.chars().next(), b.chars().next()) {
+                (None, None) => return Ordering::Equal,
+                (a_char, b_char) => a_char.cmp(&b_char),
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        let step = |s: &str, digits: &str| match digits.len() {
+            0 => s.chars().next().map_or(0, char::len_utf8),
+            n => n,
+        };
+        a = &a[step(a, a_digits)..];
+        b = &b[step(b, b_digits)..];
+    }
+}
+
+fn digit_prefix(s: &str) -> &str {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    &s[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_ids_numerically() {
+        let mut ids = vec!["ID10", "ID9", "ID09", "ID1a", "ID", "ID1"];
+        ids.sort_by(|a, b| compare_ids(a, b));
+        assert_eq!(ids, vec!["ID", "ID1", "ID1a", "ID9", "ID09", "ID10"]);
+    }
+
+    #[test]
+    fn formats_data_files() {
+        let mut contents = vec![0xff, 0xfe];
+        let document = "<?xml version=\"1.0\" encoding=\"utf-16\"?>\r\n\
+            <!-- exported 2022-09-01 -->\r\n\
+            <CARDNAME_STRING timestamp=\"1662000000\">\r\n\
+            \t<CARDNAME_STRING_ITEM id=\"ID10\">Shivan Dragon</CARDNAME_STRING_ITEM>\r\n\
+            \t<CARDNAME_STRING_ITEM id=\"ID9\">Llanowar Elves</CARDNAME_STRING_ITEM>\r\n\
+            </CARDNAME_STRING>\r\n\
+            <?exporter version=\"2\"?>";
+        for unit in document.encode_utf16() {
+            contents.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        let formatter = Formatter {
+            volatile_attributes: vec!["timestamp".to_string()],
+            ..Formatter::default()
+        };
+        let formatted = formatter.format(&contents).unwrap();
+        assert_eq!(
+            formatted,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <CARDNAME_STRING>\n  \
+             <CARDNAME_STRING_ITEM id=\"ID9\">Llanowar Elves</CARDNAME_STRING_ITEM>\n  \
+             <CARDNAME_STRING_ITEM id=\"ID10\">Shivan Dragon</CARDNAME_STRING_ITEM>\n\
+             </CARDNAME_STRING>\n"
+        );
+        assert_eq!(formatter.format(formatted.as_bytes()).unwrap(), formatted);
+
+        let kept = Formatter::default()
+            .format(b"<A timestamp=\"1\" Timestamp=\"2\" />")
+            .unwrap();
+        assert!(kept.contains("timestamp=\"1\"") && kept.contains("Timestamp=\"2\""));
+    }
+
+    #[test]
+    fn sorts_attributes_and_keeps_unkeyed_order() {
+        let formatted = Formatter::default()
+            .format(
+                b"<DigitalObjects>\
+                  <DigitalObject DigitalObjectCatalogID=\"DOC_2\" b=\"1\" a=\"2\">\
+                  <Z/><Y/></DigitalObject>\
+                  <DigitalObject DigitalObjectCatalogID=\"DOC_1\"/>\
+                  </DigitalObjects>",
+            )
+            .unwrap();
+        assert_eq!(
+            formatted,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <DigitalObjects>\n  \
+             <DigitalObject DigitalObjectCatalogID=\"DOC_1\" />\n  \
+             <DigitalObject DigitalObjectCatalogID=\"DOC_2\" a=\"2\" b=\"1\">\n    \
+             <Z />\n    \
+             <Y />\n  \
+             </DigitalObject>\n\
+             </DigitalObjects>\n"
+        );
+    }
+}
@@ -9,7 +9,20 @@
     unused_results
 )]
 
-use clickonce::deploymentmanifest::DeploymentManifest;
+use clickonce::applicationmanifest::ApplicationManifest;
+use clickonce::appref::ApplicationReference;
+use clickonce::certificate::Certificate;
+use clickonce::deploymentmanifest::{
+    DependencyType, DependentAssembly, DeploymentManifest, Update,
+};
+use clickonce::timestamp::Timestamp;
+use clickonce::version::Version;
+use clickonce::{FromXML, ParseLimits};
+use format::Formatter;
+use output::OutputDirectory;
+use reqwest::Url;
+use std::convert::TryFrom;
+use std::path::PathBuf;
 
 /// Errors returned by this library
 #[derive(thiserror::Error, Debug)]
@@ -22,36 +35,583 @@ pub enum Error {
     #[error("XML error")]
     XML(#[from] serde_xml_rs::Error),
 
+    /// ClickOnce parsing error
+    #[error("ClickOnce error")]
+    ClickOnce(#[from] clickonce::Error),
+
+    /// JSON error
+    #[error("JSON error")]
+    JSON(#[from] serde_json::Error),
+
+    /// The fetched deployment looks like a rollback of one seen previously
+    #[error("Deployment at {url} rolled back: {changes:?}")]
+    Rollback {
+        /// Deployment URL
+        url: String,
+        /// Suspicious differences from the deployment seen previously
+        changes: Vec<state::Change>,
+    },
+
+    /// Date formatting error
+    #[error("Date formatting error")]
+    Time(#[from] time::error::Format),
+
+    /// I/O error
+    #[error("I/O error")]
+    IO(#[from] std::io::Error),
+
+    /// The application manifest's size or hash differs from the deployment
+    #[error("Application manifest does not match the deployment: {0}")]
+    ApplicationManifestMismatch(String),
+
+    /// A downloaded file's size or hash differs from the application manifest
+    #[error("{name} does not match the application manifest: {reason}")]
+    FileMismatch {
+        /// Name of the file in the manifest
+        name: String,
+        /// What differs
+        reason: String,
+    },
+
+    /// The card data XML is malformed or refers to missing strings
+    #[error("Card data error: {0}")]
+    CardData(String),
+
+    /// A mana cost in the card data could not be parsed
+    #[error("Invalid mana cost {0}")]
+    InvalidManaCost(String),
+
+    /// A URL in or for a manifest could not be parsed
+    #[error("Invalid URL {0}")]
+    InvalidUrl(String),
+
     /// Generic error
     #[error("Generic error {0}")]
     Generic(String),
 }
 
+pub mod carddata;
+pub mod cards;
+pub mod format;
+pub mod output;
+pub mod state;
+
+/// URL of the Magic: the Gathering Online deployment manifest
+pub const DEFAULT_URL: &str = "http://mtgoclientdepot.onlinegaming.wizards.com/MTGO.application";
+
 /// Structure containing parsed information from Magic: the Gathering Online's ClickOnce application
 #[derive(Debug)]
 pub struct MtgoApplication {
-    #[allow(dead_code)]
     deployment_manifest: DeploymentManifest,
+    application_manifest: Option<ApplicationManifest>,
+    url: Option<String>,
 }
 
 impl MtgoApplication {
     /// Get the application from the default URL
     pub async fn default() -> Result<Self, Error> {
-        Self::from_url("http://mtgoclientdepot.onlinegaming.wizards.com/MTGO.application").await
+        Self::from_url(DEFAULT_URL).await
     }
 
     /// Get the application from a custom URL.
     pub async fn from_url(url: &str) -> Result<Self, Error> {
-        let contents = reqwest::get(url).await?.text().await?;
-        Self::from_contents(&contents)
+        Self::from_url_with_limits(url, &ParseLimits::default()).await
+    }
+
+    /// Get the application from a custom URL, rejecting responses that break
+    /// the given parsing limits.
+    ///
+    /// The application manifest named by the deployment is fetched too, and
+    /// checked against the size and hash the deployment records for it.
+    pub async fn from_url_with_limits(url: &str, limits: &ParseLimits) -> Result<Self, Error> {
+        let contents = fetch(url, limits).await?;
+        let application = Self::from_bytes_with_limits(&contents, limits)?.with_source_url(url);
+        let contents = fetch(application.application_manifest_url()?.as_str(), limits).await?;
+        application.with_application_manifest(&contents, limits)
+    }
+
+    /// Get the application that an `.appref-ms` shortcut was installed from,
+    /// checking that the deployment at its URL is the one it refers to.
+    ///
+    /// If that deployment names a different deployment provider, the
+    /// application is fetched again from the provider, which
+    /// [`source_url`](Self::source_url) then gives.
+    pub async fn from_shortcut(reference: &ApplicationReference) -> Result<Self, Error> {
+        let application = Self::from_url(&reference.codebase).await?;
+        let resolved = reference.resolve(&application.deployment_manifest)?;
+        if resolved == reference.codebase {
+            return Ok(application);
+        }
+        let application = Self::from_url(&resolved).await?;
+        let _ = reference.resolve(&application.deployment_manifest)?;
+        Ok(application)
+    }
+
+    /// Get the application from raw file contents in any supported encoding.
+    pub fn from_bytes(contents: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes_with_limits(contents, &ParseLimits::default())
+    }
+
+    /// Get the application from raw file contents, rejecting documents that
+    /// break the given parsing limits.
+    pub fn from_bytes_with_limits(contents: &[u8], limits: &ParseLimits) -> Result<Self, Error> {
+        let deployment_manifest = DeploymentManifest::from_xml_bytes_with_limits(contents, limits)?;
+        Ok(Self {
+            deployment_manifest,
+            application_manifest: None,
+            url: None,
+        })
     }
 
     /// Get the application from file contents.
     pub fn from_contents(contents: &str) -> Result<Self, Error> {
-        // Parse the contents after trimming any BOM marker
-        let deployment_manifest = serde_xml_rs::from_str(contents.trim_start_matches('\u{feff}'))?;
+        Self::from_contents_with_limits(contents, &ParseLimits::default())
+    }
+
+    /// Get the application from file contents, rejecting documents that
+    /// break the given parsing limits.
+    pub fn from_contents_with_limits(contents: &str, limits: &ParseLimits) -> Result<Self, Error> {
+        let deployment_manifest = DeploymentManifest::from_xml_with_limits(contents, limits)?;
         Ok(Self {
             deployment_manifest,
+            application_manifest: None,
+            url: None,
         })
     }
+
+    /// Set the URL the deployment manifest was fetched from, which relative
+    /// URLs in it are resolved against. Applications fetched with
+    /// [`from_url`](Self::from_url) already have this set.
+    pub fn with_source_url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    /// Add the application manifest named by the deployment, from raw file
+    /// contents, after checking them against the size and hash the
+    /// deployment records.
+    pub fn with_application_manifest(
+        mut self,
+        contents: &[u8],
+        limits: &ParseLimits,
+    ) -> Result<Self, Error> {
+        let dependency = self.install_dependency().ok_or_else(|| {
+            Error::ApplicationManifestMismatch("deployment names no application".to_string())
+        })?;
+        carddata::check_contents(dependency.size, dependency.hash.as_ref(), contents)
+            .map_err(Error::ApplicationManifestMismatch)?;
+
+        self.application_manifest = Some(ApplicationManifest::from_xml_bytes_with_limits(
+            contents, limits,
+        )?);
+        Ok(self)
+    }
+
+    /// The URL the deployment manifest was fetched from, if known.
+    pub fn source_url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// The parsed deployment manifest.
+    pub fn deployment_manifest(&self) -> &DeploymentManifest {
+        &self.deployment_manifest
+    }
+
+    /// The parsed application manifest, if it has been fetched.
+    pub fn application_manifest(&self) -> Option<&ApplicationManifest> {
+        self.application_manifest.as_ref()
+    }
+
+    /// The version of MTGO being deployed.
+    pub fn version(&self) -> Result<Version, Error> {
+        Ok(self
+            .deployment_manifest
+            .assembly
+            .assembly_identity
+            .version
+            .parse()?)
+    }
+
+    /// The product name shown in the Start menu.
+    pub fn product(&self) -> &str {
+        &self.deployment_manifest.assembly.description.product
+    }
+
+    /// The publisher name shown in the Start menu.
+    pub fn publisher(&self) -> &str {
+        &self.deployment_manifest.assembly.description.publisher
+    }
+
+    /// The oldest version clients may keep running, if the deployment
+    /// forces an upgrade.
+    pub fn minimum_required_version(&self) -> Result<Option<Version>, Error> {
+        let deployment = self.deployment_manifest.assembly.deployment.as_ref();
+        match deployment.and_then(|d| d.minimum_required_version.as_deref()) {
+            Some(version) => Ok(Some(version.parse()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// When installed clients check for updates. Returns `None` if the
+    /// application is not installed, so always runs the latest version from
+    /// the server.
+    pub fn update_policy(&self) -> Option<&Update> {
+        match &self.deployment_manifest.assembly.deployment {
+            Some(deployment) if deployment.install => Some(&deployment.subscription.update),
+            _ => None,
+        }
+    }
+
+    /// The URL of the application manifest, resolved against the URL the
+    /// deployment manifest was fetched from, or its deployment provider if
+    /// that is not known.
+    pub fn application_manifest_url(&self) -> Result<Url, Error> {
+        let codebase = self
+            .install_dependency()
+            .and_then(|assembly| assembly.codebase.as_deref())
+            .ok_or_else(|| Error::Generic("No application manifest in deployment".to_string()))?;
+
+        let provider = self
+            .deployment_manifest
+            .assembly
+            .deployment
+            .as_ref()
+            .and_then(|deployment| deployment.deployment_provider.as_ref())
+            .map(|provider| provider.codebase.as_str());
+        let base =
+            self.url.as_deref().or(provider).ok_or_else(|| {
+                Error::InvalidUrl("no URL to resolve manifests against".to_string())
+            })?;
+        let base = Url::parse(base).map_err(|e| Error::InvalidUrl(format!("{}: {}", base, e)))?;
+        base.join(&codebase.replace('\\', "/"))
+            .map_err(|e| Error::InvalidUrl(format!("{}: {}", codebase, e)))
+    }
+
+    /// The URL of a file named in the application manifest.
+    ///
+    /// Names are relative to the application manifest, and carry a `.deploy`
+    /// extension on the server if the deployment maps file extensions.
+    pub fn file_url(&self, name: &str) -> Result<Url, Error> {
+        let map_file_extensions = self
+            .deployment_manifest
+            .assembly
+            .deployment
+            .as_ref()
+            .is_some_and(|deployment| deployment.map_file_extensions);
+        let mut path = name.replace('\\', "/");
+        if map_file_extensions {
+            path.push_str(".deploy");
+        }
+        self.application_manifest_url()?
+            .join(&path)
+            .map_err(|e| Error::InvalidUrl(format!("{}: {}", name, e)))
+    }
+
+    /// Download the card data files named in the application manifest,
+    /// check each against its size and hash, and write them to `output`,
+    /// formatted by `formatter` if one is given. Returns the paths written.
+    pub async fn download_card_data(
+        &self,
+        output: &mut OutputDirectory,
+        limits: &ParseLimits,
+        formatter: Option<&Formatter>,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let manifest = self.application_manifest.as_ref().ok_or_else(|| {
+            Error::Generic("Application manifest has not been fetched".to_string())
+        })?;
+        let mut written = Vec::new();
+        for file in carddata::card_data_files(manifest) {
+            let contents = fetch(self.file_url(file.name())?.as_str(), limits).await?;
+            file.verify(&contents)?;
+            let path = match formatter {
+                Some(formatter) => {
+                    output.write(file.name(), formatter.format(&contents)?.as_bytes())?
+                }
+                None => output.write(file.name(), &contents)?,
+            };
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// The dependency naming the application manifest to install.
+    fn install_dependency(&self) -> Option<&DependentAssembly> {
+        self.deployment_manifest
+            .assembly
+            .dependency
+            .iter()
+            .map(|dependency| &dependency.dependent_assembly)
+            .find(|assembly| {
+                matches!(assembly.dependency_type, DependencyType::Install)
+                    && assembly.codebase.is_some()
+            })
+    }
+
+    /// The certificate that signed the deployment, checked against the
+    /// manifest's publisher identity. Returns `None` for unsigned deployments.
+    pub fn publisher_certificate(&self) -> Result<Option<Certificate>, Error> {
+        let assembly = &self.deployment_manifest.assembly;
+        match (&assembly.signature, &assembly.publisher_identity) {
+            (Some(signature), Some(identity)) => Ok(Some(signature.verify_publisher(identity)?)),
+            (Some(signature), None) => Ok(signature.publisher_certificate()?),
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// The parsed manifests as pretty-printed JSON, for consumers in other
+    /// languages.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(&ManifestsJson {
+            deployment_manifest: &self.deployment_manifest,
+            application_manifest: self.application_manifest.as_ref(),
+        })?)
+    }
+
+    /// The timestamp countersignature of the deployment manifest, if any,
+    /// decoded but not verified.
+    pub fn timestamp(&self) -> Result<Option<Timestamp>, Error> {
+        match &self.deployment_manifest.assembly.signature {
+            Some(signature) => Ok(signature.timestamp()?),
+            None => Ok(None),
+        }
+    }
+
+    /// Check the deployment against the one last fetched from `url`,
+    /// recording it and its certificate in `state` if it is not a rollback.
+    /// A certificate that cannot be decoded is not recorded.
+    pub fn check_rollback(
+        &self,
+        url: &str,
+        state: &mut state::DeploymentState,
+        policy: state::RollbackPolicy,
+    ) -> Result<(), Error> {
+        if !state.check(url, &self.deployment_manifest, policy)? {
+            return Ok(());
+        }
+        if let Ok(Some(certificate)) = self.publisher_certificate() {
+            let version = &self.deployment_manifest.assembly.assembly_identity.version;
+            state.record_certificate(url, version, &certificate)?;
+        }
+        Ok(())
+    }
+}
+
+/// JSON Schema of the output of [`MtgoApplication::to_json`].
+#[cfg(feature = "schema")]
+pub fn json_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(ManifestsJson<'static>)
+}
+
+/// JSON export of the manifests making up an application
+#[derive(serde::Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+struct ManifestsJson<'a> {
+    deployment_manifest: &'a DeploymentManifest,
+    application_manifest: Option<&'a ApplicationManifest>,
+}
+
+/// Download a document, giving up as soon as it exceeds the size limit.
+async fn fetch(url: &str, limits: &ParseLimits) -> Result<Vec<u8>, Error> {
+    let mut response = reqwest::get(url).await?;
+    if let Some(length) = response.content_length() {
+        limits.check_size(usize::try_from(length).unwrap_or(usize::MAX))?;
+    }
+
+    let mut contents = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        contents.extend_from_slice(&chunk);
+        limits.check_size(contents.len())?;
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> MtgoApplication {
+        MtgoApplication::from_bytes(include_bytes!(
+            "../../clickonce/src/example/sample.application"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn exposes_manifest_details() {
+        let mtgo = sample();
+        assert_eq!(mtgo.version().unwrap(), Version::new(1, 0, 0, 0));
+        assert_eq!(mtgo.product(), "My Application");
+        assert_eq!(mtgo.publisher(), "My Company Name");
+        assert_eq!(mtgo.minimum_required_version().unwrap(), None);
+        assert!(matches!(
+            mtgo.update_policy(),
+            Some(Update::Expiration(expiration)) if expiration.unit == "days"
+        ));
+
+        // The sample's deployment provider is a UNC path, not a URL
+        assert!(mtgo.application_manifest_url().is_err());
+        let mtgo = mtgo.with_source_url("http://example.com/deploy/MyApplication.application");
+        assert_eq!(
+            mtgo.application_manifest_url().unwrap().as_str(),
+            "http://example.com/deploy/1.0.0.0/My%20Application%20Deployment.exe.manifest"
+        );
+    }
+
+    #[test]
+    fn records_certificates_only_with_the_baseline() {
+        use state::{DeploymentState, RollbackPolicy};
+
+        let url = "http://example.com/deploy/MyApplication.application";
+        let mut newer = sample();
+        newer.deployment_manifest.assembly.assembly_identity.version = "2.0.0.0".to_string();
+        let mut state = DeploymentState::default();
+        newer
+            .check_rollback(url, &mut state, RollbackPolicy::Refuse)
+            .unwrap();
+        sample()
+            .check_rollback(url, &mut state, RollbackPolicy::Warn)
+            .unwrap();
+
+        let certificates = state.certificates(url).unwrap();
+        assert_eq!(certificates.keys().collect::<Vec<_>>(), vec!["2.0.0.0"]);
+    }
+
+    /// Signs the live MTGO deployment manifest, which is signed with the
+    /// .NET tooling, and checks that its signatures verify here and use the
+    /// same algorithms and transforms as ours. Run with `--ignored`.
+    #[tokio::test]
+    #[ignore = "fetches the live MTGO deployment manifest"]
+    async fn signs_like_dotnet() {
+        use clickonce::digest::DigestAlgorithm;
+        use clickonce::dom::Element;
+        use clickonce::signing::{sign, verify, SigningKey};
+
+        const DSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+        fn algorithms(element: &Element, found: &mut Vec<String>) {
+            for name in &[
+                "CanonicalizationMethod",
+                "SignatureMethod",
+                "Transform",
+                "DigestMethod",
+            ] {
+                if element.is(DSIG, name) {
+                    found.push(format!(
+                        "{} {}",
+                        name,
+                        element.attribute("Algorithm").unwrap_or_default()
+                    ));
+                }
+            }
+            for child in element.elements() {
+                algorithms(child, found);
+            }
+        }
+
+        let contents = fetch(DEFAULT_URL, &ParseLimits::default()).await.unwrap();
+        let _ = verify(&contents).expect("MTGO signature should verify");
+        let mut theirs = Vec::new();
+        algorithms(&Element::parse(&contents).unwrap(), &mut theirs);
+
+        let algorithm = if theirs.iter().any(|a| a.contains("rsa-sha256")) {
+            DigestAlgorithm::Sha256
+        } else {
+            DigestAlgorithm::Sha1
+        };
+        let key = SigningKey::from_pem(include_str!("../../clickonce/src/example/signing-key.pem"))
+            .unwrap();
+        let signed = sign(&contents, &key, algorithm).unwrap();
+        let _ = verify(signed.as_bytes()).unwrap();
+        let mut ours = Vec::new();
+        algorithms(&Element::parse(signed.as_bytes()).unwrap(), &mut ours);
+        assert_eq!(ours, theirs);
+    }
+
+    #[test]
+    fn follows_application_manifest() {
+        use clickonce::digest::DigestAlgorithm;
+        use clickonce::dom::Element;
+        use clickonce::update::update_deployment_manifest;
+
+        let application = include_bytes!("../../clickonce/src/example/sample.exe.manifest");
+        let mut deployment = Element::parse(include_bytes!(
+            "../../clickonce/src/example/sample.application"
+        ))
+        .unwrap();
+        update_deployment_manifest(&mut deployment, application, DigestAlgorithm::Sha256).unwrap();
+        let deployment = deployment.to_document();
+        let limits = ParseLimits::default();
+
+        // The sample's recorded hash is for a different application manifest
+        assert!(matches!(
+            sample().with_application_manifest(application, &limits),
+            Err(Error::ApplicationManifestMismatch(_))
+        ));
+
+        let mut tampered = application.to_vec();
+        let index = tampered.len() - 3;
+        tampered[index] ^= 1;
+        assert!(matches!(
+            MtgoApplication::from_bytes(deployment.as_bytes())
+                .unwrap()
+                .with_application_manifest(&tampered, &limits),
+            Err(Error::ApplicationManifestMismatch(_))
+        ));
+
+        let mtgo = MtgoApplication::from_bytes(deployment.as_bytes())
+            .unwrap()
+            .with_application_manifest(application, &limits)
+            .unwrap();
+        let manifest = mtgo.application_manifest().unwrap();
+        assert_eq!(manifest.assembly.file.len(), 3);
+    }
+
+    #[test]
+    fn resolves_file_urls() {
+        let mtgo = sample().with_source_url("http://example.com/deploy/sample.application");
+        assert_eq!(
+            mtgo.file_url("CardDataSource\\client_DMU.xml")
+                .unwrap()
+                .as_str(),
+            "http://example.com/deploy/1.0.0.0/CardDataSource/client_DMU.xml"
+        );
+    }
+
+    #[test]
+    fn exports_json() {
+        let mtgo = sample();
+        let json: serde_json::Value = serde_json::from_str(&mtgo.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json["deploymentManifest"]["description"]["product"],
+            "My Application"
+        );
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn json_matches_schema() {
+        let mtgo = sample();
+        let json: serde_json::Value = serde_json::from_str(&mtgo.to_json().unwrap()).unwrap();
+        let schema = serde_json::to_value(json_schema()).unwrap();
+        let compiled = jsonschema::JSONSchema::compile(&schema).unwrap();
+        assert!(compiled.is_valid(&json));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn json_schema_is_current() {
+        let generated = serde_json::to_string_pretty(&json_schema()).unwrap() + "\n";
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("schema")
+            .join("manifests.schema.json");
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            checked_in == generated,
+            "{} is out of date; rerun the tests with UPDATE_SCHEMA=1",
+            path.display()
+        );
+    }
 }
@@ -1,14 +1,240 @@
 //! Tool to download and format the XML data files from Magic: the Gathering Online
 
-use log::info;
+use clap::Parser;
+use clickonce::appref::ApplicationReference;
+use clickonce::installation::Installation;
+use clickonce::ParseLimits;
+use log::{info, warn};
+use mtgoxml::cards::types::TypeLine;
+use mtgoxml::cards::CardDatabase;
+use mtgoxml::format::Formatter;
+use mtgoxml::output::OutputDirectory;
+use mtgoxml::state::{DeploymentState, RollbackPolicy};
 use mtgoxml::MtgoApplication;
+use std::path::{Path, PathBuf};
+
+/// Command line arguments
+#[derive(Parser, Debug)]
+#[clap(version, about)]
+struct Args {
+    /// URL of the deployment manifest
+    #[clap(long, default_value = mtgoxml::DEFAULT_URL)]
+    url: String,
+
+    /// `.appref-ms` shortcut to take the deployment URL from, instead of --url
+    #[clap(long, conflicts_with = "url")]
+    shortcut: Option<PathBuf>,
+
+    /// Write the parsed manifests to this file as JSON
+    #[clap(long)]
+    json: Option<PathBuf>,
+
+    /// Download the card data XML files into this directory
+    #[clap(long)]
+    output: Option<PathBuf>,
+
+    /// Write the card data exactly as downloaded, instead of formatting it
+    #[clap(long)]
+    raw: bool,
+
+    /// Attribute to remove when formatting the card data, as it changes
+    /// between releases without the data changing. May be repeated
+    #[clap(long = "volatile-attribute", value_name = "NAME")]
+    volatile_attributes: Vec<String>,
+
+    /// Load the downloaded card data and warn about cards it cannot read
+    #[clap(long, requires = "output")]
+    card_report: bool,
+
+    /// Write the JSON Schema of the --json output to this file, and exit
+    #[cfg(feature = "schema")]
+    #[clap(long)]
+    json_schema: Option<PathBuf>,
+
+    /// Copy of an `Apps\2.0` directory to check for installed deployments,
+    /// offline, instead of fetching one
+    #[clap(long)]
+    installation: Option<PathBuf>,
+
+    /// File recording the deployments seen on previous runs
+    #[clap(long, default_value = "mtgoxml-state.json")]
+    state: PathBuf,
+
+    /// Warn about, rather than refuse, deployments that look like a rollback
+    #[clap(long)]
+    allow_rollback: bool,
+
+    /// Accept a changed public key token or publisher certificate, such as
+    /// after a key rotation, as the baseline for later runs
+    #[clap(long, conflicts_with = "allow-rollback")]
+    accept_publisher: bool,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     env_logger::init();
+    let args = Args::parse();
 
-    let mtgo = MtgoApplication::default().await?;
-    info!("Application is {:#?}", mtgo);
+    #[cfg(feature = "schema")]
+    if let Some(path) = &args.json_schema {
+        std::fs::write(path, serde_json::to_string_pretty(&mtgoxml::json_schema())?)?;
+        return Ok(());
+    }
+
+    if let Some(apps) = &args.installation {
+        return check_installation(apps);
+    }
+
+    let mtgo = match &args.shortcut {
+        Some(path) => {
+            let reference = ApplicationReference::from_bytes(&std::fs::read(path)?)?;
+            info!("Shortcut {} refers to {}", path.display(), reference);
+            MtgoApplication::from_shortcut(&reference).await?
+        }
+        None => MtgoApplication::from_url(&args.url).await?,
+    };
+    let url = mtgo.source_url().unwrap_or(&args.url).to_string();
+    info!(
+        "{} {} from {}",
+        mtgo.product(),
+        mtgo.version()?,
+        mtgo.publisher()
+    );
+    if let Some(minimum) = mtgo.minimum_required_version()? {
+        info!("Clients older than {} must update", minimum);
+    }
+    info!(
+        "Application manifest is at {}",
+        mtgo.application_manifest_url()?
+    );
+    if let Some(manifest) = mtgo.application_manifest() {
+        info!(
+            "Application manifest verified, listing {} files",
+            manifest.assembly.file.len()
+        );
+    }
+
+    let certificate = mtgo.publisher_certificate()?;
+    match &certificate {
+        Some(certificate) => info!(
+            "Signed by {} (issuer {}, serial {}, thumbprint {}, valid {} to {})",
+            certificate.subject,
+            certificate.issuer,
+            certificate.serial_number,
+            certificate.thumbprint,
+            certificate.not_before,
+            certificate.not_after
+        ),
+        None => warn!("Deployment is not signed"),
+    }
+
+    match (mtgo.timestamp()?, &certificate) {
+        (Some(timestamp), Some(certificate)) => {
+            info!(
+                "Timestamp (not verified) claims signing at {} by {}",
+                timestamp.claimed_time,
+                timestamp
+                    .authority
+                    .as_deref()
+                    .unwrap_or("an unknown authority")
+            );
+            if !timestamp.claimed_within_validity(certificate) {
+                warn!("Timestamp claims signing outside the certificate's validity window");
+            }
+        }
+        (None, Some(_)) => warn!("Deployment signature is not timestamped"),
+        _ => {}
+    }
+
+    let policy = if args.allow_rollback {
+        RollbackPolicy::Warn
+    } else if args.accept_publisher {
+        RollbackPolicy::AcceptPublisher
+    } else {
+        RollbackPolicy::Refuse
+    };
+    let mut state = DeploymentState::load(&args.state)?;
+    mtgo.check_rollback(&url, &mut state, policy)?;
+    state.save(&args.state)?;
+
+    if let Some(path) = &args.json {
+        std::fs::write(path, mtgo.to_json()?)?;
+        info!("Wrote manifests to {}", path.display());
+    }
+
+    if let Some(path) = &args.output {
+        let mut output = OutputDirectory::new(path);
+        let formatter = Formatter {
+            volatile_attributes: args.volatile_attributes.clone(),
+            ..Formatter::default()
+        };
+        let formatter = if args.raw { None } else { Some(&formatter) };
+        let written = mtgo
+            .download_card_data(&mut output, &ParseLimits::default(), formatter)
+            .await?;
+        info!(
+            "Wrote {} card data files to {}",
+            written.len(),
+            path.display()
+        );
+        if args.card_report {
+            report_cards(&path.join("CardDataSource"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Load the card data in a `CardDataSource` folder and warn about anything
+/// that cannot be read. The files are already written, so nothing here fails
+/// the run.
+fn report_cards(path: &Path) {
+    let cards = match CardDatabase::from_directory(path, &ParseLimits::default()) {
+        Ok(cards) => cards,
+        Err(e) => {
+            warn!("Could not load the card data in {}: {}", path.display(), e);
+            return;
+        }
+    };
+    info!("Loaded {} cards", cards.len());
+    for card in cards.cards() {
+        if !card.type_lines.iter().all(TypeLine::is_valid) {
+            warn!(
+                "{} has unknown types: {}",
+                card.name,
+                card.type_line_text.as_deref().unwrap_or_default()
+            );
+        }
+    }
+}
 
+/// Report the deployments installed in a copy of the ClickOnce store, and
+/// any of their files that are missing or corrupt.
+fn check_installation(apps: &Path) -> Result<(), anyhow::Error> {
+    let installation = Installation::read(apps)?;
+    for error in &installation.errors {
+        warn!("Skipped {}: {}", error.path.display(), error.error);
+    }
+    if installation.deployments.is_empty() {
+        warn!("No deployments found in {}", apps.display());
+    }
+    for deployment in &installation.deployments {
+        let identity = &deployment.manifest.assembly.assembly_identity;
+        info!(
+            "Found {} version {} in {}",
+            identity.name,
+            identity.version,
+            deployment.manifest_path.display()
+        );
+        match &deployment.application {
+            Some(application) if application.folder.is_some() => {
+                for file in deployment.problems() {
+                    warn!("{}: {:?}", file.name, file.status);
+                }
+            }
+            Some(_) => warn!("Files for {} are not installed", identity.name),
+            None => warn!("Application manifest for {} is missing", identity.name),
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,71 @@
+//! Writing files named by manifests into an output directory
+//!
+//! Every path is checked with [`clickonce::pathsafety`] before anything is
+//! written, so a hostile manifest cannot write outside the directory or
+//! overwrite one file with another of a different case.
+use crate::Error;
+use clickonce::pathsafety::SafePaths;
+use std::path::{Path, PathBuf};
+
+/// Output directory for files downloaded from a manifest
+#[derive(Debug)]
+pub struct OutputDirectory {
+    root: PathBuf,
+    paths: SafePaths,
+}
+
+impl OutputDirectory {
+    /// Create an output directory rooted at `root`.
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            paths: SafePaths::new(),
+        }
+    }
+
+    /// The root of the output directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Write a file named by a manifest, creating any parent directories.
+    /// Returns the path the file was written to.
+    pub fn write(&mut self, name: &str, contents: &[u8]) -> Result<PathBuf, Error> {
+        let path = self.root.join(self.paths.insert(name)?);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_safe_paths_only() {
+        let root = tempfile::tempdir().unwrap();
+        let mut output = OutputDirectory::new(root.path());
+
+        let written = output
+            .write("CardDataSource\\client_DMU.xml", b"<a/>")
+            .unwrap();
+        assert_eq!(
+            written,
+            root.path().join("CardDataSource").join("client_DMU.xml")
+        );
+        assert_eq!(std::fs::read(written).unwrap(), b"<a/>");
+
+        assert!(matches!(
+            output.write("..\\escape.xml", b""),
+            Err(Error::ClickOnce(clickonce::Error::UnsafePath { .. }))
+        ));
+        assert!(matches!(
+            output.write("carddatasource\\other.xml", b""),
+            Err(Error::ClickOnce(clickonce::Error::UnsafePath { .. }))
+        ));
+        assert!(!root.path().join("carddatasource").exists());
+    }
+}
@@ -0,0 +1,555 @@
+//! Persisted record of the deployments seen on previous runs
+//!
+//! A ClickOnce client refuses a deployment manifest whose version is older
+//! than the one it has installed. [`DeploymentState`] gives mtgoxml the same
+//! protection by remembering the version, public key token and publisher of
+//! the last deployment fetched from each URL.
+use crate::Error;
+use clickonce::certificate::Certificate;
+use clickonce::deploymentmanifest::DeploymentManifest;
+use clickonce::version::Version;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+
+/// What to do when a fetched deployment looks like a rollback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollbackPolicy {
+    /// Fail with [`Error::Rollback`]
+    Refuse,
+    /// Log a warning and carry on
+    Warn,
+    /// Accept a new public key token or publisher certificate, such as after
+    /// a key rotation, and record it as the baseline for later runs.
+    /// Downgrades are still refused.
+    AcceptPublisher,
+}
+
+/// Identifying details of a deployment seen on a previous run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeenDeployment {
+    /// Version of the deployment
+    pub version: String,
+    /// Public key token of the deployment
+    pub public_key_token: String,
+    /// Distinguished name of the publisher that signed the deployment
+    pub publisher: Option<String>,
+    /// Hash of the public key of the publisher certificate's issuer
+    pub issuer_key_hash: Option<String>,
+    /// Thumbprint of the certificate that signed the deployment
+    #[serde(default)]
+    pub certificate_thumbprint: Option<String>,
+}
+
+impl SeenDeployment {
+    /// Extract the identifying details of a deployment manifest. A
+    /// certificate that cannot be decoded is recorded as unknown, as is a
+    /// missing one.
+    pub fn from_manifest(manifest: &DeploymentManifest) -> Self {
+        let identity = &manifest.assembly.assembly_identity;
+        let publisher = manifest.assembly.publisher_identity.as_ref();
+        let certificate = manifest
+            .assembly
+            .signature
+            .as_ref()
+            .and_then(|signature| signature.publisher_certificate().ok().flatten());
+        Self {
+            version: identity.version.clone(),
+            public_key_token: identity.public_key_token.clone(),
+            publisher: publisher.map(|p| p.name.clone()),
+            issuer_key_hash: publisher.map(|p| p.issuer_key_hash.clone()),
+            certificate_thumbprint: certificate.map(|c| c.thumbprint),
+        }
+    }
+}
+
+/// Details of the certificate that signed a release
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateRecord {
+    /// Distinguished name of the certificate's subject
+    pub subject: String,
+    /// Distinguished name of the certificate's issuer
+    pub issuer: String,
+    /// Serial number of the certificate
+    pub serial_number: String,
+    /// SHA-1 thumbprint of the certificate
+    pub thumbprint: String,
+    /// Start of the validity window, in RFC 3339 format
+    pub not_before: String,
+    /// End of the validity window, in RFC 3339 format
+    pub not_after: String,
+}
+
+impl CertificateRecord {
+    /// Record the details of a certificate.
+    pub fn from_certificate(certificate: &Certificate) -> Result<Self, Error> {
+        Ok(Self {
+            subject: certificate.subject.clone(),
+            issuer: certificate.issuer.clone(),
+            serial_number: certificate.serial_number.clone(),
+            thumbprint: certificate.thumbprint.clone(),
+            not_before: certificate.not_before.format(&Rfc3339)?,
+            not_after: certificate.not_after.format(&Rfc3339)?,
+        })
+    }
+}
+
+/// A difference between a fetched deployment and the one seen previously
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The fetched version is lower than the one seen previously
+    Downgrade {
+        /// Version seen previously
+        previous: String,
+        /// Version just fetched
+        current: String,
+    },
+    /// The deployment is signed with a different key
+    PublicKeyToken {
+        /// Token seen previously
+        previous: String,
+        /// Token just fetched
+        current: String,
+    },
+    /// The deployment is signed with a different publisher certificate
+    Publisher {
+        /// Publisher seen previously
+        previous: String,
+        /// Publisher just fetched
+        current: String,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Downgrade { previous, current } => {
+                write!(f, "version downgraded from {} to {}", previous, current)
+            }
+            Self::PublicKeyToken { previous, current } => {
+                write!(
+                    f,
+                    "public key token changed from {} to {}",
+                    previous, current
+                )
+            }
+            Self::Publisher { previous, current } => {
+                write!(f, "publisher changed from {} to {}", previous, current)
+            }
+        }
+    }
+}
+
+/// The last deployment seen from each deployment URL, and the certificate
+/// that signed each release
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DeploymentState {
+    deployments: BTreeMap<String, SeenDeployment>,
+    #[serde(default)]
+    certificates: BTreeMap<String, BTreeMap<String, CertificateRecord>>,
+}
+
+/// Whether two hex strings, such as key hashes or thumbprints, are equal
+/// ignoring case.
+fn same_hex(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+fn describe_publisher(deployment: &SeenDeployment) -> String {
+    let mut description = match (&deployment.publisher, &deployment.issuer_key_hash) {
+        (Some(name), Some(hash)) => format!("{} (issuer key {})", name, hash),
+        (Some(name), None) => name.clone(),
+        _ => "unsigned".to_string(),
+    };
+    if let Some(thumbprint) = &deployment.certificate_thumbprint {
+        description.push_str(&format!(" certificate {}", thumbprint));
+    }
+    description
+}
+
+impl DeploymentState {
+    /// Load the state from a file, or start afresh if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        match std::fs::read(path) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save the state to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The deployment last seen from `url`, if any.
+    pub fn get(&self, url: &str) -> Option<&SeenDeployment> {
+        self.deployments.get(url)
+    }
+
+    /// The certificates recorded for each release fetched from `url`, keyed
+    /// by version.
+    pub fn certificates(&self, url: &str) -> Option<&BTreeMap<String, CertificateRecord>> {
+        self.certificates.get(url)
+    }
+
+    /// Record the certificate that signed a release fetched from `url`.
+    pub fn record_certificate(
+        &mut self,
+        url: &str,
+        version: &str,
+        certificate: &Certificate,
+    ) -> Result<(), Error> {
+        let _ = self
+            .certificates
+            .entry(url.to_string())
+            .or_default()
+            .insert(
+                version.to_string(),
+                CertificateRecord::from_certificate(certificate)?,
+            );
+        Ok(())
+    }
+
+    /// Compare a fetched deployment against the one last seen from `url`.
+    pub fn changes(&self, url: &str, manifest: &DeploymentManifest) -> Result<Vec<Change>, Error> {
+        let current = SeenDeployment::from_manifest(manifest);
+        let previous = match self.deployments.get(url) {
+            Some(previous) => previous,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut changes = Vec::new();
+        if current.version.parse::<Version>()? < previous.version.parse::<Version>()? {
+            changes.push(Change::Downgrade {
+                previous: previous.version.clone(),
+                current: current.version.clone(),
+            });
+        }
+        if !same_hex(
+            Some(&current.public_key_token),
+            Some(&previous.public_key_token),
+        ) {
+            changes.push(Change::PublicKeyToken {
+                previous: previous.public_key_token.clone(),
+                current: current.public_key_token.clone(),
+            });
+        }
+        if current.publisher != previous.publisher
+            || !same_hex(
+                current.issuer_key_hash.as_deref(),
+                previous.issuer_key_hash.as_deref(),
+            )
+            // State files written before thumbprints were recorded have
+            // none, which says nothing about the certificate.
+            || (previous.certificate_thumbprint.is_some()
+                && !same_hex(
+                    current.certificate_thumbprint.as_deref(),
+                    previous.certificate_thumbprint.as_deref(),
+                ))
+        {
+            changes.push(Change::Publisher {
+                previous: describe_publisher(previous),
+                current: describe_publisher(&current),
+            });
+        }
+        Ok(changes)
+    }
+
+    /// Check a fetched deployment against the one last seen from `url`, and
+    /// record it as the last seen deployment if nothing suspicious changed.
+    /// Returns whether it was recorded.
+    ///
+    /// With [`RollbackPolicy::Warn`] suspicious changes are logged but the
+    /// previous record is kept, so that a rollback cannot lower the baseline
+    /// for later runs. With [`RollbackPolicy::AcceptPublisher`] a new key or
+    /// publisher replaces the previous record, but a downgrade is refused.
+    pub fn check(
+        &mut self,
+        url: &str,
+        manifest: &DeploymentManifest,
+        policy: RollbackPolicy,
+    ) -> Result<bool, Error> {
+        let changes = self.changes(url, manifest)?;
+        if changes.is_empty() {
+            let _ = self
+                .deployments
+                .insert(url.to_string(), SeenDeployment::from_manifest(manifest));
+            return Ok(true);
+        }
+
+        match policy {
+            RollbackPolicy::Refuse => Err(Error::Rollback {
+                url: url.to_string(),
+                changes,
+            }),
+            RollbackPolicy::Warn => {
+                for change in &changes {
+                    warn!("Deployment at {}: {}", url, change);
+                }
+                Ok(false)
+            }
+            RollbackPolicy::AcceptPublisher => {
+                if changes
+                    .iter()
+                    .any(|change| matches!(change, Change::Downgrade { .. }))
+                {
+                    return Err(Error::Rollback {
+                        url: url.to_string(),
+                        changes,
+                    });
+                }
+                for change in &changes {
+                    warn!("Deployment at {}: accepting {}", url, change);
+                }
+                let _ = self
+                    .deployments
+                    .insert(url.to_string(), SeenDeployment::from_manifest(manifest));
+                Ok(true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clickonce::FromXML;
+
+    const URL: &str = "http://example.com/MTGO.application";
+
+    fn manifest(version: &str, token: &str, publisher: &str) -> DeploymentManifest {
+        let contents = format!(
+            r#"<assembly manifestVersion="1.0">
+  <assemblyIdentity name="MTGO.application" version="{}" publicKeyToken="{}" processorArchitecture="msil" />
+  <description publisher="Wizards of the Coast" product="Magic The Gathering Online" />
+  <compatibleFrameworks><framework targetVersion="4.7.2" profile="Full" supportedRuntime="4.0.30319" /></compatibleFrameworks>
+  <dependency>
+    <dependentAssembly dependencyType="install" codebase="MTGO.exe.manifest" size="1">
+      <assemblyIdentity name="MTGO.exe" version="{}" publicKeyToken="{}" processorArchitecture="msil" />
+    </dependentAssembly>
+  </dependency>
+  <publisherIdentity name="{}" issuerKeyHash="0123456789abcdef" />
+</assembly>"#,
+            version, token, version, token, publisher
+        );
+        DeploymentManifest::from_xml_bytes(contents.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn records_and_accepts_upgrades() {
+        let mut state = DeploymentState::default();
+        assert!(state
+            .check(
+                URL,
+                &manifest("3.4.1.0", "aa", "CN=W"),
+                RollbackPolicy::Refuse,
+            )
+            .unwrap());
+        assert!(state
+            .check(
+                URL,
+                &manifest("3.4.2.0", "AA", "CN=W"),
+                RollbackPolicy::Refuse,
+            )
+            .unwrap());
+        assert_eq!(state.get(URL).unwrap().version, "3.4.2.0");
+    }
+
+    #[test]
+    fn refuses_rollbacks() {
+        let mut state = DeploymentState::default();
+        assert!(state
+            .check(
+                URL,
+                &manifest("3.4.2.0", "aa", "CN=W"),
+                RollbackPolicy::Refuse,
+            )
+            .unwrap());
+
+        match state.check(
+            URL,
+            &manifest("3.4.1.0", "bb", "CN=X"),
+            RollbackPolicy::Refuse,
+        ) {
+            Err(Error::Rollback { changes, .. }) => assert_eq!(changes.len(), 3),
+            other => panic!("Expected a rollback error, got {:?}", other),
+        }
+
+        assert!(!state
+            .check(
+                URL,
+                &manifest("3.4.1.0", "aa", "CN=W"),
+                RollbackPolicy::Warn,
+            )
+            .unwrap());
+        assert_eq!(state.get(URL).unwrap().version, "3.4.2.0");
+    }
+
+    #[test]
+    fn accepts_new_publishers_when_asked() {
+        let mut state = DeploymentState::default();
+        assert!(state
+            .check(
+                URL,
+                &manifest("3.4.1.0", "aa", "CN=W"),
+                RollbackPolicy::Refuse,
+            )
+            .unwrap());
+
+        let rotated = manifest("3.4.2.0", "bb", "CN=X");
+        assert!(!state.check(URL, &rotated, RollbackPolicy::Warn).unwrap());
+        assert_eq!(state.get(URL).unwrap().public_key_token, "aa");
+        assert!(state
+            .check(URL, &rotated, RollbackPolicy::AcceptPublisher)
+            .unwrap());
+        assert_eq!(state.get(URL).unwrap().public_key_token, "bb");
+        assert!(state.check(URL, &rotated, RollbackPolicy::Refuse).unwrap());
+
+        assert!(matches!(
+            state.check(
+                URL,
+                &manifest("3.4.1.0", "cc", "CN=X"),
+                RollbackPolicy::AcceptPublisher
+            ),
+            Err(Error::Rollback { .. })
+        ));
+        assert_eq!(state.get(URL).unwrap().version, "3.4.2.0");
+    }
+
+    #[test]
+    fn compares_hex_fields_ignoring_case() {
+        let mut state = DeploymentState::default();
+        assert!(state
+            .check(
+                URL,
+                &manifest("3.4.1.0", "aa", "CN=W"),
+                RollbackPolicy::Refuse,
+            )
+            .unwrap());
+
+        let mut upper = manifest("3.4.1.0", "AA", "CN=W");
+        upper
+            .assembly
+            .publisher_identity
+            .as_mut()
+            .unwrap()
+            .issuer_key_hash = "0123456789ABCDEF".to_string();
+        assert_eq!(state.changes(URL, &upper).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn persists_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let mut state = DeploymentState::load(&path).unwrap();
+        assert!(state
+            .check(
+                URL,
+                &manifest("3.4.2.0", "aa", "CN=W"),
+                RollbackPolicy::Refuse,
+            )
+            .unwrap());
+        state.save(&path).unwrap();
+
+        let state = DeploymentState::load(&path).unwrap();
+        assert_eq!(
+            state.get(URL),
+            Some(&SeenDeployment {
+                version: "3.4.2.0".to_string(),
+                public_key_token: "aa".to_string(),
+                publisher: Some("CN=W".to_string()),
+                issuer_key_hash: Some("0123456789abcdef".to_string()),
+                certificate_thumbprint: None,
+            })
+        );
+    }
+
+    #[test]
+    fn loads_state_without_thumbprints() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{
+  "deployments": {{
+    "{}": {{
+      "version": "1.0.0.0",
+      "public_key_token": "43cb1e8e7a352766",
+      "publisher": "CN=Example Corp, O=Example Corp, L=Renton, S=Washington, C=US",
+      "issuer_key_hash": "eb352f8f1933b4e8b86e8b056b79b1812156881f"
+    }}
+  }}
+}}"#,
+                URL
+            ),
+        )
+        .unwrap();
+        let manifest = DeploymentManifest::from_xml_bytes(include_bytes!(
+            "../../clickonce/src/example/sample.application"
+        ))
+        .unwrap();
+
+        let mut state = DeploymentState::load(&path).unwrap();
+        assert_eq!(state.get(URL).unwrap().certificate_thumbprint, None);
+        assert_eq!(state.changes(URL, &manifest).unwrap(), Vec::new());
+        assert!(state.check(URL, &manifest, RollbackPolicy::Refuse).unwrap());
+        assert_eq!(
+            state.get(URL).unwrap().certificate_thumbprint.as_deref(),
+            Some("65EF79B694FEC7DD4F069D860D099FBC775A71E5")
+        );
+    }
+
+    #[test]
+    fn treats_undecodable_certificates_as_unknown() {
+        let contents = include_str!("../../clickonce/src/example/sample.application")
+            .replace("<X509Certificate>MII", "<X509Certificate>AAA");
+        let manifest = DeploymentManifest::from_xml_bytes(contents.as_bytes()).unwrap();
+        let signature = manifest.assembly.signature.as_ref().unwrap();
+        assert!(signature.publisher_certificate().is_err());
+
+        let seen = SeenDeployment::from_manifest(&manifest);
+        assert_eq!(seen.certificate_thumbprint, None);
+        let mut state = DeploymentState::default();
+        assert!(state.check(URL, &manifest, RollbackPolicy::Refuse).unwrap());
+    }
+
+    #[test]
+    fn records_certificates() {
+        let manifest = DeploymentManifest::from_xml_bytes(include_bytes!(
+            "../../clickonce/src/example/sample.application"
+        ))
+        .unwrap();
+        let certificate = manifest
+            .assembly
+            .signature
+            .as_ref()
+            .unwrap()
+            .publisher_certificate()
+            .unwrap()
+            .unwrap();
+
+        let mut state = DeploymentState::default();
+        assert!(state.check(URL, &manifest, RollbackPolicy::Refuse).unwrap());
+        state
+            .record_certificate(URL, "1.0.0.0", &certificate)
+            .unwrap();
+
+        assert_eq!(
+            state.get(URL).unwrap().certificate_thumbprint.as_deref(),
+            Some("65EF79B694FEC7DD4F069D860D099FBC775A71E5")
+        );
+        let record = &state.certificates(URL).unwrap()["1.0.0.0"];
+        assert_eq!(record.serial_number, "1A2B3C4D");
+        assert_eq!(record.not_before, "2022-01-01T00:00:00Z");
+    }
+}